@@ -0,0 +1,258 @@
+use std::fmt;
+
+use cpal::{
+    Device, SampleFormat, SupportedBufferSize, SupportedStreamConfigRange,
+    traits::{DeviceTrait, HostTrait},
+};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+pub const DEFAULT_BUFFER_SIZE: u32 = 512;
+
+// What the user asked for, unset values use the defaults
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub device: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub buffer_size: Option<u32>,
+}
+
+// What an output actually runs with, sources render at this rate
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputConfig {
+    pub device: String,
+    pub sample_rate: u32,
+    pub buffer_size: Option<u32>, // None when the device picks
+    pub channels: u16,
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    pub channels: Vec<u16>,
+    pub min_rate: u32,
+    pub max_rate: u32,
+}
+
+impl OutputSettings {
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE)
+    }
+
+    pub fn buffer_size(&self) -> u32 {
+        self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE)
+    }
+
+    // Used where there is no device to ask
+    pub fn requested(&self, device: &str) -> OutputConfig {
+        OutputConfig {
+            device: device.to_string(),
+            sample_rate: self.sample_rate(),
+            buffer_size: Some(self.buffer_size()),
+            channels: 2,
+        }
+    }
+}
+
+impl OutputConfig {
+    // Frames per render call, the default when the device picks
+    pub fn buffer_size(&self) -> u32 {
+        self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE)
+    }
+}
+
+impl fmt::Display for OutputConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let channels = match self.channels {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            n => format!("{}ch", n),
+        };
+        match self.buffer_size {
+            Some(frames) => write!(
+                f,
+                "{} {} Hz {} {} frames",
+                self.device, self.sample_rate, channels, frames
+            ),
+            None => write!(f, "{} {} Hz {}", self.device, self.sample_rate, channels),
+        }
+    }
+}
+
+fn device_name(device: &Device) -> String {
+    device.name().unwrap_or("unknown".to_string())
+}
+
+pub fn list_output_devices() -> Vec<DeviceInfo> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().map(|d| device_name(&d));
+    let Ok(devices) = host.output_devices() else {
+        return Vec::new();
+    };
+
+    devices
+        .map(|device| {
+            let ranges: Vec<SupportedStreamConfigRange> = device
+                .supported_output_configs()
+                .map(|configs| configs.collect())
+                .unwrap_or_default();
+            let mut channels: Vec<u16> = ranges.iter().map(|r| r.channels()).collect();
+            channels.sort();
+            channels.dedup();
+
+            let name = device_name(&device);
+            DeviceInfo {
+                is_default: default.as_ref() == Some(&name),
+                name,
+                channels,
+                min_rate: ranges
+                    .iter()
+                    .map(|r| r.min_sample_rate().0)
+                    .min()
+                    .unwrap_or(0),
+                max_rate: ranges
+                    .iter()
+                    .map(|r| r.max_sample_rate().0)
+                    .max()
+                    .unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+// The named device, or the default one when no name is given
+pub fn find_device(name: Option<&str>) -> Result<Device, String> {
+    let host = cpal::default_host();
+    match name {
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| e.to_string())?
+            .find(|device| device_name(device) == name)
+            .ok_or(format!("Output device \"{}\" not found", name)),
+        None => host
+            .default_output_device()
+            .ok_or("No output device available".to_string()),
+    }
+}
+
+// Pick the closest supported config to the request. Sources render f32, so only those
+// ranges are used. One with the wanted rate comes first, then stereo, then more
+// channels over mono so nothing is folded down.
+pub fn negotiate(
+    device: &str,
+    ranges: &[SupportedStreamConfigRange],
+    settings: &OutputSettings,
+) -> Result<OutputConfig, String> {
+    let wanted = settings.sample_rate();
+    let contains = |range: &SupportedStreamConfigRange| {
+        (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&wanted)
+    };
+
+    let range = ranges
+        .iter()
+        .filter(|range| range.sample_format() == SampleFormat::F32)
+        .max_by_key(|range| {
+            (
+                contains(range),
+                range.channels() == 2,
+                range.channels() > 2,
+                std::cmp::Reverse(range.channels()),
+            )
+        })
+        .ok_or(format!("{} has no 32-bit float output", device))?;
+
+    let sample_rate = wanted.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+    let buffer_size = match range.buffer_size() {
+        SupportedBufferSize::Range { min, max } => Some(settings.buffer_size().clamp(*min, *max)),
+        SupportedBufferSize::Unknown => None,
+    };
+
+    Ok(OutputConfig {
+        device: device.to_string(),
+        sample_rate,
+        buffer_size,
+        channels: range.channels(),
+    })
+}
+
+// Open a device and agree a config with it
+pub fn open_device(settings: &OutputSettings) -> Result<(Device, OutputConfig), String> {
+    let device = find_device(settings.device.as_deref())?;
+    let name = device_name(&device);
+    let ranges: Vec<SupportedStreamConfigRange> = device
+        .supported_output_configs()
+        .map_err(|e| e.to_string())?
+        .collect();
+    let config = negotiate(&name, &ranges, settings)?;
+    Ok((device, config))
+}
+
+// Interleave a stereo buffer into any channel count. Mono gets the average,
+// extra channels stay silent.
+pub fn interleave(left: &[f32], right: &[f32], channels: usize, data: &mut [f32]) {
+    for (i, frame) in data.chunks_mut(channels).enumerate() {
+        let (l, r) = (left[i], right[i]);
+        match frame {
+            [mono] => *mono = (l + r) * 0.5,
+            [first, second, rest @ ..] => {
+                *first = l;
+                *second = r;
+                rest.fill(0.0);
+            }
+            [] => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::SampleRate;
+
+    fn range(
+        channels: u16,
+        min: u32,
+        max: u32,
+        format: SampleFormat,
+    ) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange::new(
+            channels,
+            SampleRate(min),
+            SampleRate(max),
+            SupportedBufferSize::Range { min: 64, max: 1024 },
+            format,
+        )
+    }
+
+    #[test]
+    fn test_negotiate_and_interleave() {
+        let settings = OutputSettings {
+            device: None,
+            sample_rate: Some(96_000),
+            buffer_size: Some(4096),
+        };
+        let ranges = [
+            range(2, 44_100, 48_000, SampleFormat::I16),
+            range(1, 8_000, 192_000, SampleFormat::F32),
+            range(6, 44_100, 48_000, SampleFormat::F32),
+        ];
+
+        // only mono has the rate, so it wins over more channels
+        let config = negotiate("card", &ranges[..], &settings).unwrap();
+        assert_eq!((config.channels, config.sample_rate), (1, 96_000));
+        assert_eq!(config.buffer_size, Some(1024));
+
+        let config = negotiate("card", &ranges[..2], &OutputSettings::default()).unwrap();
+        assert_eq!((config.channels, config.sample_rate), (1, 44_100));
+        assert!(negotiate("card", &ranges[..1], &settings).is_err());
+
+        let mut data = [9.0; 6];
+        interleave(&[1.0, 0.5], &[0.0, 0.5], 3, &mut data);
+        assert_eq!(data, [1.0, 0.0, 0.0, 0.5, 0.5, 0.0]);
+        let mut data = [9.0; 2];
+        interleave(&[1.0, 0.5], &[0.0, 0.5], 1, &mut data);
+        assert_eq!(data, [0.5, 0.5]);
+    }
+}
@@ -0,0 +1,94 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+// Centre of the swept delay
+const BASE_DELAY_MS: f32 = 12.0;
+// Longest delay the buffer has to hold, base plus the widest depth
+const MAX_DELAY_MS: f32 = 25.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChorusSettings {
+    pub enabled: bool,
+    pub mix: f32,   // 0.0 dry - 1.0 wet
+    pub rate: f32,  // Hz
+    pub depth: f32, // ms
+}
+
+impl Default for ChorusSettings {
+    fn default() -> Self {
+        ChorusSettings {
+            enabled: false,
+            mix: 0.5,
+            rate: 0.8,
+            depth: 3.0,
+        }
+    }
+}
+
+// A delay swept by a slow sine, a quarter cycle apart between channels
+pub struct Chorus {
+    sample_rate: f32,
+    settings: ChorusSettings,
+    buffers: [Vec<f32>; 2],
+    write: usize,
+    phase: f32,
+}
+
+impl Chorus {
+    pub fn new(settings: &ChorusSettings, sample_rate: u32) -> Self {
+        let length = (MAX_DELAY_MS * sample_rate as f32 / 1_000.0) as usize + 2;
+        Chorus {
+            sample_rate: sample_rate as f32,
+            settings: settings.clone(),
+            buffers: [vec![0.0; length], vec![0.0; length]],
+            write: 0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn set(&mut self, settings: &ChorusSettings) {
+        self.settings = settings.clone();
+    }
+
+    pub fn clear(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.fill(0.0);
+        }
+    }
+
+    // Linear interpolation between the two samples around the delay
+    fn read(&self, channel: usize, delay: f32) -> f32 {
+        let buffer = &self.buffers[channel];
+        let length = buffer.len();
+        let position = (self.write + length) as f32 - delay;
+        let index = position.floor() as usize;
+        let fraction = position.fract();
+        let a = buffer[index % length];
+        let b = buffer[(index + 1) % length];
+        a + (b - a) * fraction
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let samples_per_ms = self.sample_rate / 1_000.0;
+        let ChorusSettings {
+            mix, rate, depth, ..
+        } = self.settings;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.buffers[0][self.write] = *l;
+            self.buffers[1][self.write] = *r;
+
+            for (channel, sample) in [l, r].into_iter().enumerate() {
+                let lfo = (TAU * (self.phase + channel as f32 * 0.25)).sin();
+                let delay = (BASE_DELAY_MS + depth * 0.5 * lfo) * samples_per_ms;
+                let wet = self.read(channel, delay);
+                *sample = *sample * (1.0 - mix) + wet * mix;
+            }
+
+            self.write = (self.write + 1) % self.buffers[0].len();
+            self.phase = (self.phase + rate / self.sample_rate) % 1.0;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+const LOW_FREQ: f32 = 250.0;
+const MID_FREQ: f32 = 1_000.0;
+const MID_Q: f32 = 0.7;
+const HIGH_FREQ: f32 = 4_000.0;
+
+// Gains in dB, 0 leaves a band alone
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EqSettings {
+    pub enabled: bool,
+    pub low_db: f32,
+    pub mid_db: f32,
+    pub high_db: f32,
+}
+
+// Filter coefficients from the RBJ audio EQ cookbook, normalised by a0
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    state: [[f32; 2]; 2], // per channel
+}
+
+impl Biquad {
+    fn set(&mut self, [b0, b1, b2, a0, a1, a2]: [f32; 6]) {
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    fn low_shelf(&mut self, freq: f32, gain_db: f32, sample_rate: f32) {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = TAU * freq / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / 2.0 * 2f32.sqrt();
+        let root = 2.0 * a.sqrt() * alpha;
+        self.set([
+            a * ((a + 1.0) - (a - 1.0) * cos + root),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - root),
+            (a + 1.0) + (a - 1.0) * cos + root,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - root,
+        ]);
+    }
+
+    fn peaking(&mut self, freq: f32, q: f32, gain_db: f32, sample_rate: f32) {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = TAU * freq / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        self.set([
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        ]);
+    }
+
+    fn high_shelf(&mut self, freq: f32, gain_db: f32, sample_rate: f32) {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = TAU * freq / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / 2.0 * 2f32.sqrt();
+        let root = 2.0 * a.sqrt() * alpha;
+        self.set([
+            a * ((a + 1.0) + (a - 1.0) * cos + root),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - root),
+            (a + 1.0) - (a - 1.0) * cos + root,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - root,
+        ]);
+    }
+
+    // Transposed direct form II
+    fn process(&mut self, channel: usize, input: f32) -> f32 {
+        let [z1, z2] = &mut self.state[channel];
+        let output = self.b0 * input + *z1;
+        *z1 = self.b1 * input - self.a1 * output + *z2;
+        *z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+// Low shelf, mid peak and high shelf
+pub struct Eq {
+    sample_rate: f32,
+    bands: [Biquad; 3],
+}
+
+impl Eq {
+    pub fn new(settings: &EqSettings, sample_rate: u32) -> Self {
+        let mut eq = Eq {
+            sample_rate: sample_rate as f32,
+            bands: [Biquad::default(); 3],
+        };
+        eq.set(settings);
+        eq
+    }
+
+    // New gains keep the filter state, so moving a band does not click
+    pub fn set(&mut self, settings: &EqSettings) {
+        let sample_rate = self.sample_rate;
+        self.bands[0].low_shelf(LOW_FREQ, settings.low_db, sample_rate);
+        self.bands[1].peaking(MID_FREQ, MID_Q, settings.mid_db, sample_rate);
+        self.bands[2].high_shelf(HIGH_FREQ, settings.high_db, sample_rate);
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            for band in &mut self.bands {
+                *l = band.process(0, *l);
+                *r = band.process(1, *r);
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimiterSettings {
+    pub enabled: bool,
+    pub threshold_db: f32,
+    pub release_ms: f32,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        LimiterSettings {
+            enabled: true,
+            threshold_db: -1.0,
+            release_ms: 100.0,
+        }
+    }
+}
+
+// Stereo-linked peak limiter. The gain drops at once and recovers over the release,
+// so the output never goes above the threshold.
+pub struct Limiter {
+    sample_rate: f32,
+    threshold: f32,
+    release: f32,
+    envelope: f32,
+}
+
+impl Limiter {
+    pub fn new(settings: &LimiterSettings, sample_rate: u32) -> Self {
+        let mut limiter = Limiter {
+            sample_rate: sample_rate as f32,
+            threshold: 1.0,
+            release: 0.0,
+            envelope: 0.0,
+        };
+        limiter.set(settings);
+        limiter
+    }
+
+    pub fn set(&mut self, settings: &LimiterSettings) {
+        self.threshold = 10f32.powf(settings.threshold_db / 20.0);
+        self.release = (-1.0 / (settings.release_ms / 1_000.0 * self.sample_rate).max(1.0)).exp();
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let peak = l.abs().max(r.abs());
+            // falling back towards the peak keeps the envelope at or above it
+            self.envelope = if peak > self.envelope {
+                peak
+            } else {
+                peak + (self.envelope - peak) * self.release
+            };
+            if self.envelope > self.threshold {
+                let gain = self.threshold / self.envelope;
+                *l *= gain;
+                *r *= gain;
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+pub mod chorus;
+pub mod eq;
+pub mod limiter;
+pub mod reverb;
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::effects::{
+    chorus::{Chorus, ChorusSettings},
+    eq::{Eq, EqSettings},
+    limiter::{Limiter, LimiterSettings},
+    reverb::{Reverb, ReverbSettings},
+};
+use crate::audio::source::AudioSource;
+
+// In processing order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    Eq,
+    Chorus,
+    Reverb,
+    Limiter,
+}
+
+// Everything adjustable from the effects panel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Param {
+    EqLow,
+    EqMid,
+    EqHigh,
+    ChorusMix,
+    ChorusRate,
+    ChorusDepth,
+    ReverbMix,
+    ReverbRoom,
+    ReverbDamping,
+    LimiterThreshold,
+    LimiterRelease,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EffectSettings {
+    pub eq: EqSettings,
+    pub chorus: ChorusSettings,
+    pub reverb: ReverbSettings,
+    pub limiter: LimiterSettings,
+}
+
+impl Effect {
+    pub fn label(self) -> &'static str {
+        match self {
+            Effect::Eq => "EQ",
+            Effect::Chorus => "Chorus",
+            Effect::Reverb => "Reverb",
+            Effect::Limiter => "Limiter",
+        }
+    }
+}
+
+impl Param {
+    pub const ALL: [Param; 11] = [
+        Param::EqLow,
+        Param::EqMid,
+        Param::EqHigh,
+        Param::ChorusMix,
+        Param::ChorusRate,
+        Param::ChorusDepth,
+        Param::ReverbMix,
+        Param::ReverbRoom,
+        Param::ReverbDamping,
+        Param::LimiterThreshold,
+        Param::LimiterRelease,
+    ];
+
+    pub fn effect(self) -> Effect {
+        match self {
+            Param::EqLow | Param::EqMid | Param::EqHigh => Effect::Eq,
+            Param::ChorusMix | Param::ChorusRate | Param::ChorusDepth => Effect::Chorus,
+            Param::ReverbMix | Param::ReverbRoom | Param::ReverbDamping => Effect::Reverb,
+            Param::LimiterThreshold | Param::LimiterRelease => Effect::Limiter,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Param::EqLow => "low",
+            Param::EqMid => "mid",
+            Param::EqHigh => "high",
+            Param::ChorusMix | Param::ReverbMix => "mix",
+            Param::ChorusRate => "rate",
+            Param::ChorusDepth => "depth",
+            Param::ReverbRoom => "room size",
+            Param::ReverbDamping => "damping",
+            Param::LimiterThreshold => "threshold",
+            Param::LimiterRelease => "release",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Param::EqLow | Param::EqMid | Param::EqHigh | Param::LimiterThreshold => "dB",
+            Param::ChorusRate => "Hz",
+            Param::ChorusDepth | Param::LimiterRelease => "ms",
+            _ => "",
+        }
+    }
+
+    // min, max and the step for one key press
+    pub fn range(self) -> (f32, f32, f32) {
+        match self {
+            Param::EqLow | Param::EqMid | Param::EqHigh => (-12.0, 12.0, 1.0),
+            Param::ChorusRate => (0.1, 5.0, 0.1),
+            Param::ChorusDepth => (0.5, 10.0, 0.5),
+            Param::LimiterThreshold => (-24.0, 0.0, 0.5),
+            Param::LimiterRelease => (10.0, 1_000.0, 10.0),
+            _ => (0.0, 1.0, 0.05),
+        }
+    }
+}
+
+impl EffectSettings {
+    pub fn value(&self, param: Param) -> f32 {
+        match param {
+            Param::EqLow => self.eq.low_db,
+            Param::EqMid => self.eq.mid_db,
+            Param::EqHigh => self.eq.high_db,
+            Param::ChorusMix => self.chorus.mix,
+            Param::ChorusRate => self.chorus.rate,
+            Param::ChorusDepth => self.chorus.depth,
+            Param::ReverbMix => self.reverb.mix,
+            Param::ReverbRoom => self.reverb.room_size,
+            Param::ReverbDamping => self.reverb.damping,
+            Param::LimiterThreshold => self.limiter.threshold_db,
+            Param::LimiterRelease => self.limiter.release_ms,
+        }
+    }
+
+    fn value_mut(&mut self, param: Param) -> &mut f32 {
+        match param {
+            Param::EqLow => &mut self.eq.low_db,
+            Param::EqMid => &mut self.eq.mid_db,
+            Param::EqHigh => &mut self.eq.high_db,
+            Param::ChorusMix => &mut self.chorus.mix,
+            Param::ChorusRate => &mut self.chorus.rate,
+            Param::ChorusDepth => &mut self.chorus.depth,
+            Param::ReverbMix => &mut self.reverb.mix,
+            Param::ReverbRoom => &mut self.reverb.room_size,
+            Param::ReverbDamping => &mut self.reverb.damping,
+            Param::LimiterThreshold => &mut self.limiter.threshold_db,
+            Param::LimiterRelease => &mut self.limiter.release_ms,
+        }
+    }
+
+    // Move a parameter by whole steps, staying in its range
+    pub fn adjust(&mut self, param: Param, steps: i32) {
+        let (min, max, step) = param.range();
+        let value = self.value_mut(param);
+        // rounded to the step so repeated presses do not drift
+        *value = ((*value + step * steps as f32) / step).round() * step;
+        *value = value.clamp(min, max);
+    }
+
+    pub fn enabled(&self, effect: Effect) -> bool {
+        match effect {
+            Effect::Eq => self.eq.enabled,
+            Effect::Chorus => self.chorus.enabled,
+            Effect::Reverb => self.reverb.enabled,
+            Effect::Limiter => self.limiter.enabled,
+        }
+    }
+
+    pub fn toggle(&mut self, effect: Effect) {
+        let enabled = match effect {
+            Effect::Eq => &mut self.eq.enabled,
+            Effect::Chorus => &mut self.chorus.enabled,
+            Effect::Reverb => &mut self.reverb.enabled,
+            Effect::Limiter => &mut self.limiter.enabled,
+        };
+        *enabled = !*enabled;
+    }
+}
+
+// EQ, chorus, reverb then limiter, each skipped while disabled
+pub struct EffectsChain {
+    settings: EffectSettings,
+    eq: Eq,
+    chorus: Chorus,
+    reverb: Reverb,
+    limiter: Limiter,
+}
+
+impl EffectsChain {
+    pub fn new(settings: EffectSettings, sample_rate: u32) -> Self {
+        EffectsChain {
+            eq: Eq::new(&settings.eq, sample_rate),
+            chorus: Chorus::new(&settings.chorus, sample_rate),
+            reverb: Reverb::new(&settings.reverb, sample_rate),
+            limiter: Limiter::new(&settings.limiter, sample_rate),
+            settings,
+        }
+    }
+
+    pub fn update(&mut self, settings: &EffectSettings) {
+        // a tail left from before it was switched off would come back
+        if settings.chorus.enabled && !self.settings.chorus.enabled {
+            self.chorus.clear();
+        }
+        if settings.reverb.enabled && !self.settings.reverb.enabled {
+            self.reverb.clear();
+        }
+        self.eq.set(&settings.eq);
+        self.chorus.set(&settings.chorus);
+        self.reverb.set(&settings.reverb);
+        self.limiter.set(&settings.limiter);
+        self.settings = settings.clone();
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        if self.settings.eq.enabled {
+            self.eq.process(left, right);
+        }
+        if self.settings.chorus.enabled {
+            self.chorus.process(left, right);
+        }
+        if self.settings.reverb.enabled {
+            self.reverb.process(left, right);
+        }
+        if self.settings.limiter.enabled {
+            self.limiter.process(left, right);
+        }
+    }
+}
+
+// Settings shared between the UI and the audio thread
+#[derive(Clone)]
+pub struct EffectsHandle {
+    settings: Arc<Mutex<EffectSettings>>,
+}
+
+impl EffectsHandle {
+    pub fn new(settings: EffectSettings) -> Self {
+        EffectsHandle {
+            settings: Arc::new(Mutex::new(settings)),
+        }
+    }
+
+    pub fn get(&self) -> EffectSettings {
+        self.settings.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn modify(&self, change: impl FnOnce(&mut EffectSettings)) {
+        if let Ok(mut settings) = self.settings.lock() {
+            change(&mut settings);
+        }
+    }
+}
+
+// A source followed by the effects chain, picking up changes made through the handle
+pub struct WithEffects<S: AudioSource> {
+    source: S,
+    chain: EffectsChain,
+    handle: EffectsHandle,
+}
+
+impl<S: AudioSource> WithEffects<S> {
+    pub fn new(source: S, handle: EffectsHandle, sample_rate: u32) -> Self {
+        WithEffects {
+            source,
+            chain: EffectsChain::new(handle.get(), sample_rate),
+            handle,
+        }
+    }
+}
+
+impl<S: AudioSource> AudioSource for WithEffects<S> {
+    fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.source.render(left, right);
+
+        // never wait on the UI from the audio thread
+        if let Ok(settings) = self.handle.settings.try_lock()
+            && *settings != self.chain.settings
+        {
+            self.chain.update(&settings);
+        }
+        self.chain.process(left, right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain() {
+        let sine: Vec<f32> = (0..4_410).map(|i| 2.0 * (i as f32 * 0.05).sin()).collect();
+
+        // with everything off the signal passes untouched
+        let mut settings = EffectSettings::default();
+        settings.toggle(Effect::Limiter);
+        let mut chain = EffectsChain::new(settings.clone(), 44_100);
+        let (mut left, mut right) = (sine.clone(), sine.clone());
+        chain.process(&mut left, &mut right);
+        assert_eq!(left, sine);
+
+        // the limiter holds every effect's output under the threshold
+        for effect in [Effect::Eq, Effect::Chorus, Effect::Reverb, Effect::Limiter] {
+            settings.toggle(effect);
+        }
+        settings.adjust(Param::EqLow, 100);
+        assert_eq!(settings.value(Param::EqLow), 12.0);
+        settings.adjust(Param::LimiterThreshold, -12);
+        assert_eq!(settings.value(Param::LimiterThreshold), -7.0);
+
+        chain.update(&settings);
+        let (mut left, mut right) = (sine.clone(), sine);
+        chain.process(&mut left, &mut right);
+        let ceiling = 10f32.powf(-7.0 / 20.0) + 1e-6;
+        assert!(left.iter().chain(&right).all(|s| s.abs() <= ceiling));
+        assert!(left.iter().any(|s| s.abs() > 0.1));
+    }
+}
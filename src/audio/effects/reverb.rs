@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+// Freeverb tunings in samples at 44.1 kHz
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+// Right channel delays are this much longer, which decorrelates the sides
+const STEREO_SPREAD: usize = 23;
+const INPUT_GAIN: f32 = 0.015;
+const WET_GAIN: f32 = 3.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReverbSettings {
+    pub enabled: bool,
+    pub mix: f32,       // 0.0 dry - 1.0 wet
+    pub room_size: f32, // 0.0 - 1.0
+    pub damping: f32,   // 0.0 - 1.0, higher loses treble faster
+}
+
+impl Default for ReverbSettings {
+    fn default() -> Self {
+        ReverbSettings {
+            enabled: false,
+            mix: 0.25,
+            room_size: 0.6,
+            damping: 0.4,
+        }
+    }
+}
+
+// Feedback delay with a low pass in the loop
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32, feedback: f32, damp: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter = output * (1.0 - damp) + self.filter * damp;
+        self.buffer[self.index] = input + self.filter * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+struct Side {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl Side {
+    fn new(sample_rate: u32, spread: usize) -> Self {
+        let scale =
+            |tuning: usize| ((tuning + spread) as u64 * sample_rate as u64 / 44_100) as usize;
+        Side {
+            combs: COMB_TUNING
+                .iter()
+                .map(|&tuning| Comb {
+                    buffer: vec![0.0; scale(tuning).max(1)],
+                    index: 0,
+                    filter: 0.0,
+                })
+                .collect(),
+            allpasses: ALLPASS_TUNING
+                .iter()
+                .map(|&tuning| Allpass {
+                    buffer: vec![0.0; scale(tuning).max(1)],
+                    index: 0,
+                })
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damp: f32) -> f32 {
+        let mut output: f32 = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process(input, feedback, damp))
+            .sum();
+        for allpass in &mut self.allpasses {
+            output = allpass.process(output);
+        }
+        output
+    }
+
+    fn clear(&mut self) {
+        for comb in &mut self.combs {
+            comb.buffer.fill(0.0);
+            comb.filter = 0.0;
+        }
+        for allpass in &mut self.allpasses {
+            allpass.buffer.fill(0.0);
+        }
+    }
+}
+
+// Schroeder-Moorer reverb after Freeverb, parallel combs into series allpasses
+pub struct Reverb {
+    settings: ReverbSettings,
+    sides: [Side; 2],
+}
+
+impl Reverb {
+    pub fn new(settings: &ReverbSettings, sample_rate: u32) -> Self {
+        Reverb {
+            settings: settings.clone(),
+            sides: [
+                Side::new(sample_rate, 0),
+                Side::new(sample_rate, STEREO_SPREAD),
+            ],
+        }
+    }
+
+    pub fn set(&mut self, settings: &ReverbSettings) {
+        self.settings = settings.clone();
+    }
+
+    pub fn clear(&mut self) {
+        for side in &mut self.sides {
+            side.clear();
+        }
+    }
+
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let feedback = 0.7 + 0.28 * self.settings.room_size;
+        let damp = 0.4 * self.settings.damping;
+        let mix = self.settings.mix;
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let input = (*l + *r) * INPUT_GAIN;
+            let wet_left = self.sides[0].process(input, feedback, damp);
+            let wet_right = self.sides[1].process(input, feedback, damp);
+            *l = *l * (1.0 - mix) + wet_left * mix * WET_GAIN;
+            *r = *r * (1.0 - mix) + wet_right * mix * WET_GAIN;
+        }
+    }
+}
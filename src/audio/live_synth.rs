@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender, channel},
+    },
+};
+
+use rustysynth::{SoundFont, Synthesizer, SynthesizerSettings};
+
+use crate::audio::{retune::Retuner, source::AudioSource};
+use crate::theory::tuning::Tuning;
+use crate::types::midi::Message;
+
+// Soundfont synth played by incoming note events
+pub struct LiveSynth {
+    synth: Synthesizer,
+    events: Receiver<Message>,
+    retuner: Option<Retuner>, // only for tunings other than 12-TET
+}
+
+// The UI side of a live synth
+#[derive(Clone)]
+pub struct LiveSynthHandle {
+    pub events: Sender<Message>,
+    polyphony: Option<usize>, // None when the synth does not count its voices
+    sounding: Arc<AtomicUsize>,
+}
+
+impl LiveSynthHandle {
+    // The receiving end goes to the synth on the audio thread
+    pub fn new(polyphony: usize) -> (Self, Receiver<Message>) {
+        let (tx, rx) = channel();
+        let handle = LiveSynthHandle {
+            events: tx,
+            polyphony: Some(polyphony),
+            sounding: Arc::new(AtomicUsize::new(0)),
+        };
+        (handle, rx)
+    }
+
+    // For synths that steal voices on their own and cannot say how many sound
+    pub fn uncounted() -> (Self, Receiver<Message>) {
+        let (mut handle, rx) = LiveSynthHandle::new(0);
+        handle.polyphony = None;
+        (handle, rx)
+    }
+
+    // Voices sounding and the most that can, when the synth keeps count
+    pub fn voices(&self) -> Option<(usize, usize)> {
+        self.polyphony
+            .map(|polyphony| (self.sounding.load(Ordering::Relaxed), polyphony))
+    }
+
+    // Shared count the synth updates as voices start and stop
+    pub fn counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.sounding)
+    }
+}
+
+impl LiveSynth {
+    pub fn new(
+        soundfont: &Path,
+        sample_rate: u32,
+        polyphony: usize,
+    ) -> Result<(Self, LiveSynthHandle), String> {
+        let file = File::open(soundfont).map_err(|e| e.to_string())?;
+        let soundfont =
+            SoundFont::new(&mut BufReader::new(file)).map_err(|e| format!("{:?}", e))?;
+
+        let mut settings = SynthesizerSettings::new(sample_rate as i32);
+        settings.maximum_polyphony = polyphony;
+        // reverb and chorus come from the effects chain
+        settings.enable_reverb_and_chorus = false;
+        let synth =
+            Synthesizer::new(&Arc::new(soundfont), &settings).map_err(|e| format!("{:?}", e))?;
+
+        // rustysynth steals voices itself, counting samples rather than keys
+        let (handle, events) = LiveSynthHandle::uncounted();
+        Ok((
+            LiveSynth {
+                synth,
+                events,
+                retuner: None,
+            },
+            handle,
+        ))
+    }
+
+    pub fn set_tuning(&mut self, tuning: Tuning) {
+        self.retuner = (!tuning.is_equal()).then(|| Retuner::new(tuning));
+    }
+
+    fn handle(&mut self, [status, data1, data2]: [u8; 3]) {
+        self.synth.process_midi_message(
+            (status & 0x0f) as i32,
+            (status & 0xf0) as i32,
+            data1 as i32,
+            data2 as i32,
+        );
+    }
+}
+
+impl AudioSource for LiveSynth {
+    fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        // events are applied at the start of each buffer
+        while let Ok((_, data)) = self.events.try_recv() {
+            let messages = match self.retuner.as_mut() {
+                Some(retuner) => retuner.handle(data),
+                None => vec![data],
+            };
+            for message in messages {
+                self.handle(message);
+            }
+        }
+        self.synth.render(left, right);
+    }
+}
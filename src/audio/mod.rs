@@ -0,0 +1,11 @@
+pub mod device;
+pub mod effects;
+pub mod live_synth;
+pub mod oscillator;
+pub mod render;
+pub mod retune;
+pub mod sink;
+pub mod soundfont;
+pub mod source;
+pub mod voices;
+pub mod wav;
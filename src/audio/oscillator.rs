@@ -0,0 +1,433 @@
+use std::{
+    f32::consts::TAU,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Receiver,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{
+    live_synth::LiveSynthHandle,
+    source::{AudioSource, SILENCE},
+    voices::{VoiceAllocator, VoiceKey},
+};
+use crate::theory::tuning::Tuning;
+use crate::types::midi::{ALL_NOTES_OFF, ALL_SOUND_OFF, Message, SUSTAIN};
+
+// Fade for stolen voices, short enough to free them quickly without a click
+const STEAL_RELEASE: f32 = 0.005;
+// Leaves headroom for chords
+const MASTER_GAIN: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Waveform {
+    Sine,
+    Saw,
+    Square,
+    Fm,
+    EPiano,
+}
+
+// Times in seconds, sustain as a level 0.0 - 1.0
+#[derive(Clone, Copy, Debug)]
+pub struct Adsr {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 5] = [
+        Waveform::Sine,
+        Waveform::Saw,
+        Waveform::Square,
+        Waveform::Fm,
+        Waveform::EPiano,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Waveform::Sine => "sine",
+            Waveform::Saw => "saw",
+            Waveform::Square => "square",
+            Waveform::Fm => "fm",
+            Waveform::EPiano => "electric piano",
+        }
+    }
+
+    pub fn envelope(self) -> Adsr {
+        match self {
+            Waveform::Sine => Adsr {
+                attack: 0.01,
+                decay: 0.3,
+                sustain: 0.8,
+                release: 0.3,
+            },
+            Waveform::Saw | Waveform::Square => Adsr {
+                attack: 0.01,
+                decay: 0.4,
+                sustain: 0.6,
+                release: 0.2,
+            },
+            Waveform::Fm => Adsr {
+                attack: 0.005,
+                decay: 1.0,
+                sustain: 0.4,
+                release: 0.4,
+            },
+            // struck and left to die away, like a tine
+            Waveform::EPiano => Adsr {
+                attack: 0.002,
+                decay: 4.0,
+                sustain: 0.0,
+                release: 0.5,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Done,
+}
+
+struct Envelope {
+    adsr: Adsr,
+    stage: Stage,
+    level: f32,
+    release_coef: f32,
+}
+
+// Per sample multiplier that takes a level to silence in `seconds`
+fn decay_coef(seconds: f32, sample_rate: f32) -> f32 {
+    (SILENCE.ln() / (seconds * sample_rate).max(1.0)).exp()
+}
+
+impl Envelope {
+    fn new(adsr: Adsr) -> Self {
+        Envelope {
+            adsr,
+            stage: Stage::Attack,
+            level: 0.0,
+            release_coef: 0.0,
+        }
+    }
+
+    fn next(&mut self, sample_rate: f32) -> f32 {
+        match self.stage {
+            Stage::Attack => {
+                self.level += 1.0 / (self.adsr.attack * sample_rate).max(1.0);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                let sustain = self.adsr.sustain;
+                self.level =
+                    sustain + (self.level - sustain) * decay_coef(self.adsr.decay, sample_rate);
+                if self.level - sustain < SILENCE {
+                    self.level = sustain;
+                    self.stage = if sustain > 0.0 {
+                        Stage::Sustain
+                    } else {
+                        Stage::Done
+                    };
+                }
+            }
+            Stage::Sustain => (),
+            Stage::Release => {
+                self.level *= self.release_coef;
+                if self.level < SILENCE {
+                    self.level = 0.0;
+                    self.stage = Stage::Done;
+                }
+            }
+            Stage::Done => self.level = 0.0,
+        }
+        self.level
+    }
+
+    fn release(&mut self, seconds: f32, sample_rate: f32) {
+        if self.stage != Stage::Done {
+            self.stage = Stage::Release;
+            self.release_coef = decay_coef(seconds, sample_rate);
+        }
+    }
+}
+
+struct Voice {
+    channel: u8,
+    key: u8,
+    frequency: f32,
+    velocity: f32, // 0.0 - 1.0
+    phase: f32,
+    mod_phase: f32,
+    bell_phase: f32,
+    tone: f32,   // brightness that fades after the strike, e-piano and fm
+    filter: f32, // low pass state for saw and square
+    envelope: Envelope,
+    sustained: bool, // key released while the pedal is down
+}
+
+// Naive waveforms alias badly, this smooths the jump at each wrap
+fn poly_blep(phase: f32, step: f32) -> f32 {
+    if phase < step {
+        let t = phase / step;
+        t + t - t * t - 1.0
+    } else if phase > 1.0 - step {
+        let t = (phase - 1.0) / step;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+impl Voice {
+    fn new(channel: u8, key: u8, frequency: f32, velocity: u8, adsr: Adsr) -> Self {
+        Voice {
+            channel,
+            key,
+            frequency,
+            velocity: velocity as f32 / 127.0,
+            phase: 0.0,
+            mod_phase: 0.0,
+            bell_phase: 0.0,
+            tone: 1.0,
+            filter: 0.0,
+            envelope: Envelope::new(adsr),
+            sustained: false,
+        }
+    }
+
+    fn next(&mut self, waveform: Waveform, sample_rate: f32) -> f32 {
+        let step = self.frequency / sample_rate;
+        let level = self.envelope.next(sample_rate);
+
+        let sample = match waveform {
+            Waveform::Sine => (TAU * self.phase).sin(),
+            Waveform::Saw => {
+                let raw = 2.0 * self.phase - 1.0 - poly_blep(self.phase, step);
+                self.low_pass(raw, sample_rate)
+            }
+            Waveform::Square => {
+                let raw = if self.phase < 0.5 { 1.0 } else { -1.0 } + poly_blep(self.phase, step)
+                    - poly_blep((self.phase + 0.5) % 1.0, step);
+                self.low_pass(raw, sample_rate)
+            }
+            Waveform::Fm => {
+                // harder playing gives a wider, brighter spectrum
+                let index = (1.0 + 4.0 * self.velocity) * level;
+                (TAU * self.phase + index * (TAU * self.mod_phase).sin()).sin()
+            }
+            Waveform::EPiano => {
+                self.tone *= decay_coef(0.8, sample_rate);
+                let index = 2.5 * self.velocity * self.tone;
+                let tine = (TAU * self.phase + index * (TAU * self.mod_phase).sin()).sin();
+                let bell = (TAU * self.bell_phase).sin() * 0.15 * self.velocity * self.tone.powi(4);
+                tine + bell
+            }
+        };
+
+        self.phase = (self.phase + step) % 1.0;
+        let mod_ratio = if waveform == Waveform::Fm { 2.0 } else { 1.0 };
+        self.mod_phase = (self.mod_phase + step * mod_ratio) % 1.0;
+        self.bell_phase = (self.bell_phase + step * 14.0) % 1.0;
+
+        // velocity to amplitude, softened so quiet notes stay audible
+        sample * level * self.velocity.powf(1.5)
+    }
+
+    // Velocity sets the cutoff, soft notes sound darker
+    fn low_pass(&mut self, input: f32, sample_rate: f32) -> f32 {
+        let cutoff = 800.0 + 9_000.0 * self.velocity;
+        let amount = 1.0 - (-TAU * cutoff / sample_rate).exp();
+        self.filter += amount * (input - self.filter);
+        self.filter
+    }
+
+    fn is_done(&self) -> bool {
+        self.envelope.stage == Stage::Done
+    }
+}
+
+// Built-in synth played by incoming note events, needs no soundfont
+pub struct OscillatorSynth {
+    sample_rate: f32,
+    waveform: Waveform,
+    voices: Vec<Voice>,
+    allocator: VoiceAllocator,
+    sustain: [bool; 16], // pedal down, by channel
+    events: Receiver<Message>,
+    sounding: Arc<AtomicUsize>,
+    tuning: Tuning,
+}
+
+impl OscillatorSynth {
+    pub fn new(waveform: Waveform, sample_rate: u32, polyphony: usize) -> (Self, LiveSynthHandle) {
+        let (handle, events) = LiveSynthHandle::new(polyphony);
+        let synth = OscillatorSynth {
+            sample_rate: sample_rate as f32,
+            waveform,
+            voices: Vec::new(),
+            allocator: VoiceAllocator::new(polyphony),
+            sustain: [false; 16],
+            events,
+            sounding: handle.counter(),
+            tuning: Tuning::equal(),
+        };
+        (synth, handle)
+    }
+
+    pub fn set_tuning(&mut self, tuning: Tuning) {
+        self.tuning = tuning;
+    }
+
+    fn release(&mut self, (channel, key): VoiceKey, seconds: f32) {
+        let sample_rate = self.sample_rate;
+        for voice in self
+            .voices
+            .iter_mut()
+            .filter(|v| v.channel == channel && v.key == key)
+        {
+            voice.envelope.release(seconds, sample_rate);
+        }
+    }
+
+    pub fn handle(&mut self, [status, data1, data2]: [u8; 3]) {
+        let adsr = self.waveform.envelope();
+        let channel = status & 0x0f;
+        match status & 0xf0 {
+            0x90 if data2 > 0 => {
+                // keys the tuning leaves unmapped stay silent
+                let Some(frequency) = self.tuning.frequency(data1) else {
+                    return;
+                };
+                // a repeated key restarts, stolen keys make room
+                self.release((channel, data1), STEAL_RELEASE);
+                for stolen in self.allocator.note_on(channel, data1) {
+                    self.release(stolen, STEAL_RELEASE);
+                }
+                self.voices
+                    .push(Voice::new(channel, data1, frequency, data2, adsr));
+            }
+            0x80 | 0x90 => {
+                self.allocator.note_off(channel, data1);
+                if self.sustain[channel as usize] {
+                    for voice in self
+                        .voices
+                        .iter_mut()
+                        .filter(|v| v.channel == channel && v.key == data1)
+                    {
+                        voice.sustained = true;
+                    }
+                } else {
+                    self.release((channel, data1), adsr.release);
+                }
+            }
+            0xb0 if data1 == SUSTAIN => {
+                self.allocator.control_change(channel, data1, data2);
+                self.sustain[channel as usize] = data2 >= 64;
+                if data2 < 64 {
+                    let sample_rate = self.sample_rate;
+                    for voice in self
+                        .voices
+                        .iter_mut()
+                        .filter(|v| v.channel == channel && v.sustained)
+                    {
+                        voice.sustained = false;
+                        voice.envelope.release(adsr.release, sample_rate);
+                    }
+                }
+            }
+            0xb0 if data1 == ALL_SOUND_OFF || data1 == ALL_NOTES_OFF => {
+                self.allocator.control_change(channel, data1, data2);
+                let sample_rate = self.sample_rate;
+                for voice in self.voices.iter_mut().filter(|v| v.channel == channel) {
+                    voice.envelope.release(STEAL_RELEASE, sample_rate);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl AudioSource for OscillatorSynth {
+    fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        while let Ok((_, data)) = self.events.try_recv() {
+            self.handle(data);
+        }
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let mut sample = 0.0;
+            for voice in &mut self.voices {
+                sample += voice.next(self.waveform, self.sample_rate);
+            }
+            *l = sample * MASTER_GAIN;
+            *r = sample * MASTER_GAIN;
+        }
+
+        self.voices.retain(|voice| !voice.is_done());
+        self.sounding.store(self.voices.len(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peak(synth: &mut OscillatorSynth, samples: usize) -> f32 {
+        let mut left = vec![0.0; samples];
+        let mut right = vec![0.0; samples];
+        synth.render(&mut left, &mut right);
+        left.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    #[test]
+    fn test_velocity_and_release() {
+        for waveform in Waveform::ALL {
+            let (mut soft, _) = OscillatorSynth::new(waveform, 44_100, 8);
+            let (mut loud, _) = OscillatorSynth::new(waveform, 44_100, 8);
+            soft.handle([0x90, 60, 30]);
+            loud.handle([0x90, 60, 120]);
+
+            let soft_peak = peak(&mut soft, 4_410);
+            let loud_peak = peak(&mut loud, 4_410);
+            assert!(soft_peak > 0.0 && loud_peak > soft_peak, "{:?}", waveform);
+
+            // the voice is freed once its release has finished
+            loud.handle([0x80, 60, 0]);
+            peak(&mut loud, 44_100);
+            assert!(loud.voices.is_empty(), "{:?}", waveform);
+        }
+    }
+
+    #[test]
+    fn test_pedal_by_channel() {
+        let (mut synth, _) = OscillatorSynth::new(Waveform::Sine, 44_100, 8);
+        synth.handle([0xb0, SUSTAIN, 127]);
+        synth.handle([0x90, 60, 100]);
+        synth.handle([0x91, 64, 100]);
+        synth.handle([0x80, 60, 0]);
+        synth.handle([0x81, 64, 0]);
+
+        // only the key on the pedal's channel keeps sounding
+        peak(&mut synth, 44_100);
+        assert_eq!(synth.voices.len(), 1);
+        synth.handle([0xb1, ALL_NOTES_OFF, 0]);
+        peak(&mut synth, 44_100);
+        assert_eq!(synth.voices.len(), 1);
+
+        synth.handle([0xb0, SUSTAIN, 0]);
+        peak(&mut synth, 44_100);
+        assert!(synth.voices.is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::mpsc::Sender;
+
+use crate::audio::source::{AudioSource, SILENCE};
+use crate::types::midi::{ALL_NOTES_OFF, Message, SUSTAIN};
+
+// Samples rendered per call, like a device buffer
+const BLOCK_SIZE: usize = 512;
+// Longest ring-out after the last event
+const MAX_TAIL_SECONDS: usize = 10;
+
+struct Output {
+    left: Vec<f32>,
+    right: Vec<f32>,
+    block_left: Vec<f32>,
+    block_right: Vec<f32>,
+}
+
+impl Output {
+    // Render until `frames` samples have been produced
+    fn render_to(&mut self, source: &mut impl AudioSource, frames: usize) {
+        while self.left.len() < frames {
+            let count = (frames - self.left.len()).min(BLOCK_SIZE);
+            self.block_left.resize(count, 0.0);
+            self.block_right.resize(count, 0.0);
+            source.render(&mut self.block_left, &mut self.block_right);
+            self.left.extend_from_slice(&self.block_left);
+            self.right.extend_from_slice(&self.block_right);
+        }
+    }
+}
+
+// Play messages through a source without a sound card. Each event is sent just before
+// the sample it falls on, so the result is the same on every run. Rendering carries on
+// after the last event until the sound has died away.
+pub fn render_offline(
+    source: &mut impl AudioSource,
+    events: &Sender<Message>,
+    messages: &[Message],
+    sample_rate: u32,
+) -> (Vec<f32>, Vec<f32>) {
+    let mut output = Output {
+        left: Vec::new(),
+        right: Vec::new(),
+        block_left: Vec::with_capacity(BLOCK_SIZE),
+        block_right: Vec::with_capacity(BLOCK_SIZE),
+    };
+    if messages.is_empty() {
+        return (output.left, output.right);
+    }
+    let start = messages.first().map(|(time, _)| *time).unwrap_or(0);
+    let end = messages.last().map(|(time, _)| *time).unwrap_or(0);
+
+    for (time, data) in messages {
+        let frame = (time - start) as u128 * sample_rate as u128 / 1_000_000;
+        output.render_to(source, frame as usize);
+        let _ = events.send((*time, *data));
+    }
+
+    // lift the pedal and release anything still held so the tail can end
+    for channel in 0..16 {
+        let _ = events.send((end, [0xb0 | channel, SUSTAIN, 0]));
+        let _ = events.send((end, [0xb0 | channel, ALL_NOTES_OFF, 0]));
+    }
+
+    let limit = output.left.len() + MAX_TAIL_SECONDS * sample_rate as usize;
+    while output.left.len() < limit {
+        let from = output.left.len();
+        output.render_to(source, from + BLOCK_SIZE);
+        let quiet = output.left[from..]
+            .iter()
+            .chain(&output.right[from..])
+            .all(|sample| sample.abs() < SILENCE);
+        if quiet {
+            break;
+        }
+    }
+
+    (output.left, output.right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::live_synth::LiveSynth;
+    use crate::audio::oscillator::{OscillatorSynth, Waveform};
+    use std::path::Path;
+
+    #[test]
+    fn test_render_offline() {
+        let messages = [(1_000_000, [0x90, 60, 100]), (1_500_000, [0x80, 60, 0])];
+        let render = || {
+            let (mut synth, handle) = OscillatorSynth::new(Waveform::Sine, 8_000, 8);
+            render_offline(&mut synth, &handle.events, &messages, 8_000)
+        };
+        let (left, right) = render();
+
+        // starts at the first event, half a second of note then the release
+        assert!(left.len() > 4_000 && left.len() < 4_000 + 8_000);
+        assert!(left[..4_000].iter().any(|s| s.abs() > 0.01));
+        assert!(
+            left.iter()
+                .rev()
+                .take(BLOCK_SIZE)
+                .all(|s| s.abs() < SILENCE)
+        );
+        assert_eq!(left, right);
+        assert_eq!(render().0, left);
+    }
+
+    #[test]
+    fn test_render_offline_tail_through_soundfont() {
+        // a key left under the pedal on channel 2 and one never released
+        let messages = [
+            (0, [0xb1, 64, 127]),
+            (0, [0x91, 60, 100]),
+            (200_000, [0x81, 60, 0]),
+            (300_000, [0x91, 64, 100]),
+        ];
+        let soundfont = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/sf2/Leonhart.sf2");
+        let (mut synth, handle) = LiveSynth::new(&soundfont, 22_050, 8).unwrap();
+        let (left, _) = render_offline(&mut synth, &handle.events, &messages, 22_050);
+
+        // the pedal and all notes off end the tail well before the limit
+        assert!(left.len() > 6_615 && left.len() < 6_615 + MAX_TAIL_SECONDS * 22_050);
+        assert!(
+            left.iter()
+                .rev()
+                .take(BLOCK_SIZE)
+                .all(|s| s.abs() < SILENCE)
+        );
+    }
+
+    #[test]
+    fn test_render_offline_empty() {
+        let (mut synth, handle) = OscillatorSynth::new(Waveform::Sine, 8_000, 8);
+        let (left, right) = render_offline(&mut synth, &handle.events, &[], 8_000);
+        assert!(left.is_empty() && right.is_empty());
+    }
+}
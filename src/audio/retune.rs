@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::theory::tuning::Tuning;
+use crate::types::midi::SUSTAIN;
+
+// General MIDI drums, played as they are
+const PERCUSSION: u8 = 9;
+const BANK_SELECT: u8 = 0;
+// Pitch bend at rest, and the steps in one semitone with the default range of two
+const BEND_CENTRE: i32 = 8192;
+const BEND_PER_SEMITONE: f64 = 4096.0;
+
+#[derive(Clone, Copy)]
+struct Channel {
+    preset: (u8, u8), // bank and program
+    bend: u16,
+    held: usize, // notes down, or held by the pedal
+    last_used: u64,
+}
+
+// Soundfont synths can only bend a whole channel, so each note goes to a channel
+// already bent to its pitch, or else to the least recently used free one. Notes that
+// share a pitch offset share a channel, so 12-note tunings need at most 12.
+pub struct Retuner {
+    tuning: Tuning,
+    channels: [Channel; 16],
+    presets: [(u8, u8); 16],              // selected on each incoming channel
+    playing: HashMap<(u8, u8), (u8, u8)>, // incoming channel and key to channel and note
+    sustained: Vec<u8>,                   // channels of notes released under the pedal
+    sustain: bool,
+    clock: u64,
+}
+
+impl Retuner {
+    pub fn new(tuning: Tuning) -> Self {
+        Retuner {
+            tuning,
+            channels: [Channel {
+                preset: (0, 0),
+                bend: BEND_CENTRE as u16,
+                held: 0,
+                last_used: 0,
+            }; 16],
+            presets: [(0, 0); 16],
+            playing: HashMap::new(),
+            sustained: Vec::new(),
+            sustain: false,
+            clock: 0,
+        }
+    }
+
+    fn pick_channel(&self, preset: (u8, u8), bend: u16) -> u8 {
+        let candidates = || (0..16u8).filter(|c| *c != PERCUSSION);
+        let ready = candidates().find(|c| {
+            let channel = &self.channels[*c as usize];
+            channel.preset == preset && channel.bend == bend
+        });
+        // otherwise a free channel, or the stalest one as a last resort
+        ready.unwrap_or_else(|| {
+            candidates()
+                .min_by_key(|c| {
+                    let channel = &self.channels[*c as usize];
+                    (channel.held > 0, channel.last_used)
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    // The messages that play this one in tune, on the synth's channels
+    pub fn handle(&mut self, [status, data1, data2]: [u8; 3]) -> Vec<[u8; 3]> {
+        let source = status & 0x0f;
+        let command = status & 0xf0;
+        if source == PERCUSSION {
+            return vec![[status, data1, data2]];
+        }
+
+        match command {
+            0x90 if data2 > 0 => {
+                let Some(pitch) = self.tuning.pitch(data1) else {
+                    return Vec::new();
+                };
+                let note = pitch.round().clamp(0.0, 127.0);
+                let bend = (BEND_CENTRE + ((pitch - note) * BEND_PER_SEMITONE).round() as i32)
+                    .clamp(0, 16_383) as u16;
+                let preset = self.presets[source as usize];
+                let index = self.pick_channel(preset, bend);
+
+                let mut out = Vec::new();
+                let channel = &mut self.channels[index as usize];
+                if channel.preset != preset {
+                    out.push([0xb0 | index, BANK_SELECT, preset.0]);
+                    out.push([0xc0 | index, preset.1, 0]);
+                    channel.preset = preset;
+                }
+                if channel.bend != bend {
+                    out.push([0xe0 | index, (bend & 0x7f) as u8, (bend >> 7) as u8]);
+                    channel.bend = bend;
+                }
+                self.clock += 1;
+                channel.held += 1;
+                channel.last_used = self.clock;
+                out.push([0x90 | index, note as u8, data2]);
+
+                // a key struck again before its release lets the old note go
+                if let Some((old, old_note)) =
+                    self.playing.insert((source, data1), (index, note as u8))
+                {
+                    self.channels[old as usize].held -= 1;
+                    out.insert(0, [0x80 | old, old_note, 0]);
+                }
+                out
+            }
+            0x80 | 0x90 => {
+                let Some((index, note)) = self.playing.remove(&(source, data1)) else {
+                    return Vec::new();
+                };
+                if self.sustain {
+                    self.sustained.push(index);
+                } else {
+                    self.channels[index as usize].held -= 1;
+                }
+                vec![[command | index, note, data2]]
+            }
+            0xa0 => match self.playing.get(&(source, data1)) {
+                Some((index, note)) => vec![[0xa0 | index, *note, data2]],
+                None => Vec::new(),
+            },
+            0xb0 if data1 == BANK_SELECT => {
+                self.presets[source as usize].0 = data2;
+                Vec::new()
+            }
+            0xc0 => {
+                self.presets[source as usize].1 = data1;
+                Vec::new()
+            }
+            // the channels' bends belong to the tuning
+            0xe0 => Vec::new(),
+            _ => {
+                if command == 0xb0 && data1 == SUSTAIN {
+                    self.sustain = data2 >= 64;
+                    if !self.sustain {
+                        for index in self.sustained.drain(..) {
+                            self.channels[index as usize].held -= 1;
+                        }
+                    }
+                }
+                // controllers reach every channel a note could be on
+                (0..16u8)
+                    .filter(|c| *c != PERCUSSION)
+                    .map(|c| [command | c, data1, data2])
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theory::tuning::Temperament;
+
+    #[test]
+    fn test_retuner_channels() {
+        let mut retuner = Retuner::new(Temperament::Just.tuning());
+        // C and E are both off 12-TET by different amounts, so they need two channels
+        let c = retuner.handle([0x90, 60, 100]);
+        let e = retuner.handle([0x90, 64, 100]);
+        assert_eq!(c.len(), 2);
+        assert_eq!(c[0][0], 0xe0);
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[0][0], 0xe1);
+        assert!(e[0][2] < c[0][2]);
+
+        // C an octave up is bent the same as C, so it joins its channel
+        assert_eq!(retuner.handle([0x90, 72, 90]), vec![[0x90, 72, 90]]);
+        assert_eq!(retuner.handle([0x80, 64, 0]), vec![[0x81, 64, 0]]);
+
+        // the preset follows the incoming channel onto the one used
+        assert!(retuner.handle([0xc0, 40, 0]).is_empty());
+        let violin = retuner.handle([0x90, 67, 100]);
+        assert!(violin.iter().any(|m| m[0] & 0xf0 == 0xc0 && m[1] == 40));
+        assert_eq!(retuner.handle([0xe0, 0, 80]), Vec::<[u8; 3]>::new());
+        assert_eq!(retuner.handle([0xb0, 64, 127]).len(), 15);
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    env,
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use cpal::{
+    BufferSize, Device, SampleRate, StreamConfig,
+    traits::{DeviceTrait, StreamTrait},
+};
+
+use crate::audio::{
+    device::{OutputConfig, OutputSettings, interleave, open_device},
+    source::AudioSource,
+    wav::WavWriter,
+};
+
+// Frames the cpal callback can render at once, the largest buffer size the output allows
+const MAX_CALLBACK_FRAMES: usize = 8_192;
+
+// Where rendered audio goes. The source must render at the sink's config,
+// `run` keeps pulling from it until `stop` is set and the thread unparked.
+pub trait AudioSink: Send {
+    fn config(&self) -> &OutputConfig;
+
+    fn run(self: Box<Self>, source: Box<dyn AudioSource>, stop: &AtomicBool) -> Result<(), String>;
+}
+
+// An output device through cpal
+pub struct CpalSink {
+    device: Device,
+    config: OutputConfig,
+}
+
+// Renders and discards, for machines without a sound card
+pub struct NullSink {
+    config: OutputConfig,
+}
+
+// Records everything the source plays to a WAV file
+pub struct FileSink {
+    path: PathBuf,
+    config: OutputConfig,
+}
+
+impl CpalSink {
+    pub fn open(settings: &OutputSettings) -> Result<Self, String> {
+        let (device, config) = open_device(settings)?;
+        Ok(CpalSink { device, config })
+    }
+}
+
+impl AudioSink for CpalSink {
+    fn config(&self) -> &OutputConfig {
+        &self.config
+    }
+
+    fn run(
+        self: Box<Self>,
+        mut source: Box<dyn AudioSource>,
+        stop: &AtomicBool,
+    ) -> Result<(), String> {
+        let channels = self.config.channels as usize;
+        let config = StreamConfig {
+            channels: self.config.channels,
+            sample_rate: SampleRate(self.config.sample_rate),
+            buffer_size: match self.config.buffer_size {
+                Some(frames) => BufferSize::Fixed(frames),
+                None => BufferSize::Default,
+            },
+        };
+
+        let err_fn = |err| eprintln!("An error occurred on the output audio stream: {}", err);
+
+        // allocated here, as the callback must not; larger requests render in parts
+        let frames = (self.config.buffer_size.unwrap_or(0) as usize).max(MAX_CALLBACK_FRAMES);
+        let mut left = vec![0f32; frames];
+        let mut right = vec![0f32; frames];
+
+        let stream = self
+            .device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    // the device may ask for a different amount than configured
+                    for data in data.chunks_mut(frames * channels) {
+                        let sample_count = data.len() / channels;
+                        let (left, right) = (&mut left[..sample_count], &mut right[..sample_count]);
+                        source.render(left, right);
+                        interleave(left, right, channels, data);
+                    }
+                },
+                err_fn,
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+
+        stream.play().map_err(|e| e.to_string())?;
+
+        // the stream plays until it is dropped
+        while !stop.load(Ordering::Relaxed) {
+            thread::park();
+        }
+        Ok(())
+    }
+}
+
+// Calls `block` once per buffer at the pace a device would, so players keep time
+fn run_paced(
+    sample_rate: u32,
+    buffer_size: u32,
+    stop: &AtomicBool,
+    mut block: impl FnMut() -> Result<(), String>,
+) -> Result<(), String> {
+    let period = Duration::from_secs_f64(buffer_size as f64 / sample_rate as f64);
+    let mut next = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        block()?;
+        next += period;
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+    Ok(())
+}
+
+impl NullSink {
+    pub fn new(settings: &OutputSettings) -> Self {
+        NullSink {
+            config: settings.requested("null"),
+        }
+    }
+}
+
+impl AudioSink for NullSink {
+    fn config(&self) -> &OutputConfig {
+        &self.config
+    }
+
+    fn run(
+        self: Box<Self>,
+        mut source: Box<dyn AudioSource>,
+        stop: &AtomicBool,
+    ) -> Result<(), String> {
+        let (sample_rate, buffer_size) = (self.config.sample_rate, self.config.buffer_size());
+        let mut left = vec![0f32; buffer_size as usize];
+        let mut right = vec![0f32; buffer_size as usize];
+        run_paced(sample_rate, buffer_size, stop, || {
+            source.render(&mut left, &mut right);
+            Ok(())
+        })
+    }
+}
+
+impl FileSink {
+    pub fn new(path: PathBuf, settings: &OutputSettings) -> Self {
+        FileSink {
+            config: settings.requested(&path.display().to_string()),
+            path,
+        }
+    }
+}
+
+impl AudioSink for FileSink {
+    fn config(&self) -> &OutputConfig {
+        &self.config
+    }
+
+    fn run(
+        self: Box<Self>,
+        mut source: Box<dyn AudioSource>,
+        stop: &AtomicBool,
+    ) -> Result<(), String> {
+        let (sample_rate, buffer_size) = (self.config.sample_rate, self.config.buffer_size());
+        let file = File::create(&self.path)
+            .map_err(|e| format!("Failed to create {}: {}", self.path.display(), e))?;
+        let mut wav =
+            WavWriter::new(BufWriter::new(file), sample_rate).map_err(|e| e.to_string())?;
+
+        let mut left = vec![0f32; buffer_size as usize];
+        let mut right = vec![0f32; buffer_size as usize];
+        run_paced(sample_rate, buffer_size, stop, || {
+            source.render(&mut left, &mut right);
+            wav.append(&left, &right).map_err(|e| e.to_string())
+        })
+    }
+}
+
+// The sink named by AUDIO_SINK: cpal (default), null or file. File sinks write to
+// AUDIO_SINK_FILE with the stream name added, e.g. audio-out-live.wav.
+// Without a usable output device cpal falls back to null so the program still runs.
+pub fn open_sink(stream: &str, settings: &OutputSettings) -> Box<dyn AudioSink> {
+    match env::var("AUDIO_SINK").unwrap_or_default().as_str() {
+        "null" => Box::new(NullSink::new(settings)),
+        "file" => {
+            let base =
+                PathBuf::from(env::var("AUDIO_SINK_FILE").unwrap_or("audio-out.wav".to_string()));
+            let stem = base
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or("audio-out".to_string());
+            Box::new(FileSink::new(
+                base.with_file_name(format!("{}-{}.wav", stem, stream)),
+                settings,
+            ))
+        }
+        _ => match CpalSink::open(settings) {
+            Ok(sink) => Box::new(sink),
+            Err(e) => {
+                eprintln!("{}, audio will not be heard.", e);
+                Box::new(NullSink::new(settings))
+            }
+        },
+    }
+}
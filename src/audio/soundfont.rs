@@ -0,0 +1,135 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use rustysynth::SoundFont;
+
+use crate::util::prefs::Prefs;
+
+#[derive(Clone, Debug)]
+pub struct PresetInfo {
+    pub bank: i32,
+    pub patch: i32,
+    pub name: String,
+}
+
+// Directories searched for .sf2 files, from SOUNDFONT_PATH (separated like PATH)
+pub fn search_paths() -> Vec<PathBuf> {
+    match env::var_os("SOUNDFONT_PATH") {
+        Some(paths) if !paths.is_empty() => env::split_paths(&paths).collect(),
+        _ => vec![PathBuf::from("src/sf2")],
+    }
+}
+
+// All .sf2 files in the search paths, valid or not, sorted by path
+pub fn find_soundfonts() -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = search_paths()
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("sf2"))
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+// A RIFF file of form "sfbk" whose chunk size matches the file
+pub fn check_header(path: &Path) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let length = file.metadata().map_err(|e| e.to_string())?.len();
+
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header)
+        .map_err(|_| "File too short".to_string())?;
+
+    if &header[0..4] != b"RIFF" {
+        return Err("Not a RIFF file".to_string());
+    }
+    if &header[8..12] != b"sfbk" {
+        return Err("Not a soundfont (sfbk) file".to_string());
+    }
+    let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+    if size + 8 > length {
+        return Err(format!(
+            "Truncated, expected {} bytes but found {}",
+            size + 8,
+            length
+        ));
+    }
+    Ok(())
+}
+
+// Presets in the file, ordered by bank then patch
+pub fn list_presets(path: &Path) -> Result<Vec<PresetInfo>, String> {
+    check_header(path)?;
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let soundfont = SoundFont::new(&mut BufReader::new(file)).map_err(|e| e.to_string())?;
+
+    let mut presets: Vec<PresetInfo> = soundfont
+        .get_presets()
+        .iter()
+        .map(|preset| PresetInfo {
+            bank: preset.get_bank_number(),
+            patch: preset.get_patch_number(),
+            name: preset.get_name().to_string(),
+        })
+        .collect();
+    presets.sort_by_key(|p| (p.bank, p.patch));
+    Ok(presets)
+}
+
+// The remembered soundfont if it is still usable, otherwise the first valid one found
+pub fn default_soundfont() -> Result<PathBuf, String> {
+    if let Some(path) = Prefs::load().soundfont
+        && check_header(&path).is_ok()
+    {
+        return Ok(path);
+    }
+
+    find_soundfonts()
+        .into_iter()
+        .find(|path| check_header(path).is_ok())
+        .ok_or(format!(
+            "No valid soundfont found in {}",
+            search_paths()
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ))
+}
+
+pub fn remember_soundfont(path: &Path) -> Result<(), String> {
+    let mut prefs = Prefs::load();
+    prefs.soundfont = Some(path.to_path_buf());
+    prefs.save().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_header() {
+        let dir = env::temp_dir();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            path
+        };
+
+        let mut valid = b"RIFF\x04\x00\x00\x00sfbk".to_vec();
+        valid.extend_from_slice(&[0; 4]);
+        assert!(check_header(&write("rust-keys-valid.sf2", &valid)).is_ok());
+        assert!(check_header(&write("rust-keys-wave.sf2", b"RIFF\x04\x00\x00\x00WAVE")).is_err());
+        assert!(check_header(&write("rust-keys-short.sf2", b"RIFF\xff\x00\x00\x00sfbk")).is_err());
+        assert!(check_header(&write("rust-keys-tiny.sf2", b"RIFF")).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use midi_player::Player;
+
+// Level treated as silence, about -80 dB
+pub const SILENCE: f32 = 0.0001;
+
+// Anything that can fill a stereo buffer from the audio thread
+pub trait AudioSource: Send {
+    fn render(&mut self, left: &mut [f32], right: &mut [f32]);
+}
+
+impl AudioSource for Player {
+    fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        Player::render(self, left, right);
+    }
+}
+
+impl<S: AudioSource + ?Sized> AudioSource for Box<S> {
+    fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        (**self).render(left, right);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::types::midi::{ALL_NOTES_OFF, ALL_SOUND_OFF, SUSTAIN};
+
+// A note as the synth knows it, (channel, key)
+pub type VoiceKey = (u8, u8);
+
+// Keeps the number of sounding notes within the polyphony, stealing the oldest
+pub struct VoiceAllocator {
+    polyphony: usize,
+    sounding: Vec<(VoiceKey, bool)>, // (note, released while the pedal is down), oldest first
+    sustain: [bool; 16],
+}
+
+impl VoiceAllocator {
+    pub fn new(polyphony: usize) -> Self {
+        VoiceAllocator {
+            polyphony: polyphony.max(1),
+            sounding: Vec::new(),
+            sustain: [false; 16],
+        }
+    }
+
+    // Returns the notes that have to be stopped to make room, each on its own channel
+    pub fn note_on(&mut self, channel: u8, key: u8) -> Vec<VoiceKey> {
+        // a repeated key reuses its voice
+        self.sounding.retain(|(note, _)| *note != (channel, key));
+
+        let mut stolen = Vec::new();
+        while self.sounding.len() >= self.polyphony {
+            // notes only held by the pedal go first
+            let index = self
+                .sounding
+                .iter()
+                .position(|(_, released)| *released)
+                .unwrap_or(0);
+            stolen.push(self.sounding.remove(index).0);
+        }
+
+        self.sounding.push(((channel, key), false));
+        stolen
+    }
+
+    pub fn note_off(&mut self, channel: u8, key: u8) {
+        if self.sustain[(channel & 0x0f) as usize] {
+            if let Some(voice) = self
+                .sounding
+                .iter_mut()
+                .find(|(note, _)| *note == (channel, key))
+            {
+                voice.1 = true;
+            }
+        } else {
+            self.sounding.retain(|(note, _)| *note != (channel, key));
+        }
+    }
+
+    pub fn control_change(&mut self, channel: u8, controller: u8, value: u8) {
+        match controller {
+            SUSTAIN => {
+                let sustain = &mut self.sustain[(channel & 0x0f) as usize];
+                *sustain = value >= 64;
+                if !*sustain {
+                    self.sounding
+                        .retain(|((c, _), released)| *c != channel || !released);
+                }
+            }
+            ALL_SOUND_OFF | ALL_NOTES_OFF => self.sounding.retain(|((c, _), _)| *c != channel),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steals_oldest_and_pedal_held_first() {
+        let mut voices = VoiceAllocator::new(3);
+        assert!(voices.note_on(0, 60).is_empty());
+        assert!(voices.note_on(0, 64).is_empty());
+        assert!(voices.note_on(0, 67).is_empty());
+        assert_eq!(voices.note_on(0, 72), vec![(0, 60)]);
+
+        // 67 is released under the pedal, so it goes before the older 64
+        voices.control_change(0, SUSTAIN, 127);
+        voices.note_off(0, 67);
+        assert_eq!(voices.note_on(0, 76), vec![(0, 67)]);
+
+        voices.note_off(0, 64);
+        voices.control_change(0, SUSTAIN, 0);
+        assert_eq!(voices.sounding.len(), 2);
+    }
+
+    #[test]
+    fn test_voices_by_channel() {
+        let mut voices = VoiceAllocator::new(2);
+        assert!(voices.note_on(1, 60).is_empty());
+        // the same key on another channel is a voice of its own
+        assert!(voices.note_on(2, 60).is_empty());
+        assert_eq!(voices.note_on(3, 64), vec![(1, 60)]);
+
+        // the pedal and all notes off only reach their own channel
+        voices.control_change(2, SUSTAIN, 127);
+        voices.note_off(3, 64);
+        voices.note_off(2, 60);
+        assert_eq!(voices.sounding.len(), 1);
+        voices.control_change(2, ALL_NOTES_OFF, 0);
+        assert_eq!(voices.sounding.len(), 0);
+    }
+}
@@ -0,0 +1,117 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+const BLOCK_ALIGN: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+// Offsets of the sizes patched as a stream grows
+const RIFF_SIZE_OFFSET: u64 = 4;
+const DATA_SIZE_OFFSET: u64 = 40;
+
+fn data_size(frames: usize) -> io::Result<u32> {
+    u32::try_from(frames * BLOCK_ALIGN as usize)
+        .map_err(|_| io::Error::other("Audio too long for a WAV file"))
+}
+
+fn write_header(writer: &mut impl Write, sample_rate: u32, data_size: u32) -> io::Result<()> {
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * BLOCK_ALIGN as u32).to_le_bytes())?;
+    writer.write_all(&BLOCK_ALIGN.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())
+}
+
+// Interleaved, samples outside -1.0 - 1.0 are clipped
+fn write_samples(writer: &mut impl Write, left: &[f32], right: &[f32]) -> io::Result<()> {
+    for (l, r) in left.iter().zip(right) {
+        for sample in [l, r] {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+// Stereo 16-bit PCM
+pub fn write_wav(
+    writer: &mut impl Write,
+    sample_rate: u32,
+    left: &[f32],
+    right: &[f32],
+) -> io::Result<()> {
+    let frames = left.len().min(right.len());
+    write_header(writer, sample_rate, data_size(frames)?)?;
+    write_samples(writer, &left[..frames], &right[..frames])?;
+    writer.flush()
+}
+
+// A WAV file written as audio arrives. The header is kept up to date after
+// every block so the file stays playable if the program is stopped.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    frames: usize,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        write_header(&mut writer, sample_rate, 0)?;
+        Ok(WavWriter { writer, frames: 0 })
+    }
+
+    pub fn append(&mut self, left: &[f32], right: &[f32]) -> io::Result<()> {
+        let frames = left.len().min(right.len());
+        let size = data_size(self.frames + frames)?;
+        write_samples(&mut self.writer, &left[..frames], &right[..frames])?;
+        self.frames += frames;
+
+        self.writer.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.writer.write_all(&(36 + size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.writer.write_all(&size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_wav() {
+        let mut bytes = Vec::new();
+        write_wav(&mut bytes, 48_000, &[0.0, 1.0, 2.0], &[-1.0, 0.5, -2.0]).unwrap();
+
+        assert_eq!(bytes.len(), 44 + 3 * 4);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 12);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(
+            u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+            48_000
+        );
+        assert_eq!(&bytes[36..40], b"data");
+
+        let samples: Vec<i16> = bytes[44..]
+            .chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(samples, vec![0, -32767, 32767, 16384, 32767, -32767]);
+
+        // streamed in blocks, the same bytes come out
+        let path = std::env::temp_dir().join("rust-keys-stream.wav");
+        let mut wav = WavWriter::new(std::fs::File::create(&path).unwrap(), 48_000).unwrap();
+        wav.append(&[0.0, 1.0], &[-1.0, 0.5]).unwrap();
+        wav.append(&[2.0], &[-2.0]).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    }
+}
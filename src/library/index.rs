@@ -0,0 +1,185 @@
+use std::{
+    env,
+    fs::{self, create_dir_all},
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use musical_note::Key;
+use serde::{Deserialize, Serialize};
+
+use crate::theory::key::key_name;
+use crate::theory::key_finding::detect_key;
+use crate::types::recording::{Recording, recording_dir};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub file: String,  // file name inside the recording directory
+    pub modified: u64, // Unix timestamp of the file, to spot edits
+    pub title: String,
+    pub recorded_at: u64,
+    pub duration: u64, // micro seconds
+    pub note_count: usize,
+    pub key: Option<Key>, // set on the recording or detected
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortBy {
+    Date,
+    Title,
+    Duration,
+    Notes,
+}
+
+impl SortBy {
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Date => SortBy::Title,
+            SortBy::Title => SortBy::Duration,
+            SortBy::Duration => SortBy::Notes,
+            SortBy::Notes => SortBy::Date,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Date => "date",
+            SortBy::Title => "title",
+            SortBy::Duration => "duration",
+            SortBy::Notes => "notes",
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+}
+
+fn index_path() -> PathBuf {
+    PathBuf::from(env::var("LIBRARY_INDEX").unwrap_or("library.ron".to_string()))
+}
+
+fn modified(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl LibraryEntry {
+    fn from_recording(file: String, modified: u64, recording: &Recording) -> Self {
+        LibraryEntry {
+            file,
+            modified,
+            title: recording.title.clone(),
+            recorded_at: recording.recorded_at,
+            duration: recording.duration(),
+            note_count: recording
+                .messages
+                .iter()
+                .filter(|(_, [status, _, velocity])| {
+                    (0x90..=0x9f).contains(status) && *velocity > 0
+                })
+                .count(),
+            key: recording.key.or_else(|| detect_key(&recording.messages)),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        recording_dir().join(&self.file)
+    }
+
+    pub fn key_label(&self) -> String {
+        self.key.map(key_name).unwrap_or("-".to_string())
+    }
+
+    // Every search term has to appear in the title, key or a tag
+    pub fn matches(&self, query: &str) -> bool {
+        let haystack = format!(
+            "{} {} {}",
+            self.title,
+            self.key_label(),
+            self.tags.join(" ")
+        )
+        .to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|term| haystack.contains(term))
+    }
+}
+
+impl Library {
+    pub fn load() -> Self {
+        fs::read_to_string(index_path())
+            .ok()
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = index_path();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            create_dir_all(parent)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+
+    // Re-index new or changed recordings and drop deleted ones, keeping tags
+    pub fn refresh(&mut self) {
+        let Ok(dir) = fs::read_dir(recording_dir()) else {
+            self.entries.clear();
+            return;
+        };
+
+        let mut entries = Vec::new();
+        for path in dir.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "ron") {
+                continue;
+            }
+            let Some(file) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let modified = modified(&path);
+            let existing = self.entries.iter().find(|e| e.file == file);
+
+            match existing {
+                Some(entry) if entry.modified == modified => entries.push(entry.clone()),
+                _ => {
+                    let Ok(recording) = Recording::load(&path) else {
+                        continue;
+                    };
+                    let mut entry = LibraryEntry::from_recording(file, modified, &recording);
+                    entry.tags = existing.map(|e| e.tags.clone()).unwrap_or_default();
+                    entries.push(entry);
+                }
+            }
+        }
+
+        self.entries = entries;
+    }
+
+    // Indexes into entries that match the query, in the given order
+    pub fn search(&self, query: &str, sort: SortBy) -> Vec<usize> {
+        let mut found: Vec<usize> = (0..self.entries.len())
+            .filter(|i| self.entries[*i].matches(query))
+            .collect();
+
+        let entries = &self.entries;
+        match sort {
+            SortBy::Date => found.sort_by_key(|i| std::cmp::Reverse(entries[*i].recorded_at)),
+            SortBy::Title => found.sort_by_key(|i| entries[*i].title.to_lowercase()),
+            SortBy::Duration => found.sort_by_key(|i| std::cmp::Reverse(entries[*i].duration)),
+            SortBy::Notes => found.sort_by_key(|i| std::cmp::Reverse(entries[*i].note_count)),
+        }
+        found
+    }
+}
@@ -8,13 +8,21 @@ use crate::{multicast::multicast::PeerNetwork, util::logger::Logger};
 mod rk_io;
 mod rk_ui;
 mod test;
+mod theory;
 mod types;
 mod util;
+mod audio;
+mod library;
 mod multicast;
+mod notation;
+mod pipeline;
+mod practice;
+mod stats;
 
 #[derive(Clone)]
 enum InputPath {
     Connect,
+    PlayAlong,
     Test,
     Options,
     Quit,
@@ -22,10 +30,12 @@ enum InputPath {
 
 fn select_input(midi: MidiInput) -> Option<MidiInputConnection<()>> {
     let result = get_input(
-        "Select path [(c)onnect | (t)est | (o)ptions | (q)uit]: ",
+        "Select path [(c)onnect | (p)lay-along | (t)est | (o)ptions | (q)uit]: ",
         &[
             ("c", InputPath::Connect),
             ("connect", InputPath::Connect),
+            ("p", InputPath::PlayAlong),
+            ("play-along", InputPath::PlayAlong),
             ("t", InputPath::Test),
             ("test", InputPath::Test),
             ("o", InputPath::Options),
@@ -37,8 +47,9 @@ fn select_input(midi: MidiInput) -> Option<MidiInputConnection<()>> {
 
     return match result.unwrap() {
         InputPath::Connect => rk_io::connect::select_device(midi),
+        InputPath::PlayAlong => rk_io::play_along::select_play_along(midi),
         InputPath::Test => rk_io::playback::select_playback(midi),
-        InputPath::Options => rk_io::opts::select_opt(),
+        InputPath::Options => rk_io::opts::select_opt(midi),
         InputPath::Quit => std::process::exit(0),
     };
 }
@@ -52,7 +63,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     // end setup
 
     let mut midi: MidiInput = MidiInput::new("midir input")?;
-    midi.ignore(Ignore::All); // sys-log messages, other data persists
+    midi.ignore(Ignore::SysexAndActiveSense); // clock is let through for the arpeggiator
 
     let ports: Vec<midir::MidiInputPort> = midi.ports();
     if ports.is_empty() {
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, fmt::Write};
+
+use musical_note::{Key, NoteName, Scale};
+
+use crate::notation::quantise::{DIVISIONS, NoteEvent, Score};
+use crate::theory::key::{SpelledPitch, mode_name, signature_alter, spell, step_char, tonic_name};
+
+// Measures per line of each voice
+const MEASURES_PER_LINE: usize = 4;
+
+// Accidentals in force in the current bar, by (letter, octave)
+type BarAccidentals = HashMap<(NoteName, i8), i8>;
+
+fn key_field(key: Key) -> String {
+    let mode = match key.scale {
+        Scale::Major => String::new(),
+        Scale::Minor => "m".to_string(),
+        scale => format!(" {}", &mode_name(scale)[..3]),
+    };
+    format!("{}{}", tonic_name(key), mode)
+}
+
+// Middle C is C, the octave above is lower case
+fn pitch(spelled: SpelledPitch, key: Key, bar: &mut BarAccidentals) -> String {
+    let mut out = String::new();
+
+    let current = bar
+        .get(&(spelled.step, spelled.octave))
+        .copied()
+        .unwrap_or_else(|| signature_alter(key, spelled.step));
+    if current != spelled.alter {
+        out.push_str(match spelled.alter {
+            -2 => "__",
+            -1 => "_",
+            1 => "^",
+            2 => "^^",
+            _ => "=",
+        });
+        bar.insert((spelled.step, spelled.octave), spelled.alter);
+    }
+
+    let letter = step_char(spelled.step);
+    if spelled.octave >= 5 {
+        out.push(letter.to_ascii_lowercase());
+        out.push_str(&"'".repeat((spelled.octave - 5) as usize));
+    } else {
+        out.push(letter);
+        out.push_str(&",".repeat((4 - spelled.octave) as usize));
+    }
+    out
+}
+
+// Lengths are written in sixteenths, the unit note length
+fn write_event(out: &mut String, key: Key, event: &NoteEvent, bar: &mut BarAccidentals) {
+    if event.is_rest() {
+        out.push('z');
+    } else if event.pitches.len() == 1 {
+        out.push_str(&pitch(spell(event.pitches[0], key), key, bar));
+    } else {
+        out.push('[');
+        for note in &event.pitches {
+            out.push_str(&pitch(spell(*note, key), key, bar));
+        }
+        out.push(']');
+    }
+
+    if event.duration != 1 {
+        let _ = write!(out, "{}", event.duration);
+    }
+    if event.tie_start {
+        out.push('-');
+    }
+    out.push(' ');
+}
+
+// ABC tune for a quantised score, one voice per staff
+pub fn to_abc(score: &Score) -> String {
+    let mut out = String::new();
+    out.push_str("X:1\n");
+    let _ = writeln!(out, "T:{}", score.title);
+    let _ = writeln!(out, "M:{}/{}", score.beats, score.beat_type);
+    let _ = writeln!(out, "L:1/{}", DIVISIONS * 4);
+    let _ = writeln!(out, "Q:1/4={}", score.bpm.round());
+    out.push_str("%%score {1 2}\n");
+    out.push_str("V:1 clef=treble\n");
+    out.push_str("V:2 clef=bass\n");
+    let _ = writeln!(out, "K:{}", key_field(score.key));
+
+    for (line, measures) in score.measures.chunks(MEASURES_PER_LINE).enumerate() {
+        let last_line = (line + 1) * MEASURES_PER_LINE >= score.measures.len();
+        for staff in 0..2 {
+            let _ = write!(out, "[V:{}] ", staff + 1);
+            for measure in measures {
+                let mut bar = BarAccidentals::new();
+                for event in &measure.staves[staff] {
+                    write_event(&mut out, score.key, event, &mut bar);
+                }
+                out.push_str("| ");
+            }
+            if last_line {
+                out.truncate(out.trim_end().len() - 1);
+                out.push_str("|]");
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accidentals_follow_key_and_bar() {
+        let key = Key::new(NoteName::F, musical_note::Accidental::White, Scale::Major);
+        let mut bar = BarAccidentals::new();
+
+        // Bb is in the key, B natural needs a sign once per bar
+        assert_eq!(pitch(spell(70, key), key, &mut bar), "B");
+        assert_eq!(pitch(spell(71, key), key, &mut bar), "=B");
+        assert_eq!(pitch(spell(71, key), key, &mut bar), "B");
+        assert_eq!(pitch(spell(70, key), key, &mut bar), "_B");
+        assert_eq!(pitch(spell(48, key), key, &mut bar), "C,");
+        assert_eq!(pitch(spell(84, key), key, &mut bar), "c'");
+    }
+}
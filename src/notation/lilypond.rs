@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+use musical_note::{Key, NoteName};
+
+use crate::notation::quantise::{NoteEvent, Score};
+use crate::theory::key::{alter, mode_name, spell};
+
+fn step_name(step: NoteName) -> &'static str {
+    match step {
+        NoteName::C => "c",
+        NoteName::D => "d",
+        NoteName::E => "e",
+        NoteName::F => "f",
+        NoteName::G => "g",
+        NoteName::A => "a",
+        NoteName::B => "b",
+    }
+}
+
+// Dutch note names: bes, fis, eeses...
+fn pitch_name(step: NoteName, alter: i8) -> String {
+    let suffix = match alter {
+        -2 => "eses",
+        -1 => "es",
+        1 => "is",
+        2 => "isis",
+        _ => "",
+    };
+    format!("{}{}", step_name(step), suffix)
+}
+
+// Absolute pitch, plain c is the octave below middle C
+fn pitch(midi: u8, key: Key) -> String {
+    let spelled = spell(midi, key);
+    let marks = spelled.octave - 3;
+    let octave = if marks > 0 {
+        "'".repeat(marks as usize)
+    } else {
+        ",".repeat(-marks as usize)
+    };
+    format!("{}{}", pitch_name(spelled.step, spelled.alter), octave)
+}
+
+fn duration(event: &NoteEvent) -> String {
+    let (base, dotted) = event.base_length();
+    format!("{}{}", 16 / base, if dotted { "." } else { "" })
+}
+
+fn write_event(out: &mut String, key: Key, event: &NoteEvent, whole_bar: bool) {
+    if event.is_rest() {
+        let rest = if whole_bar { "R" } else { "r" };
+        let _ = write!(out, "{}{} ", rest, duration(event));
+        return;
+    }
+
+    if event.pitches.len() == 1 {
+        out.push_str(&pitch(event.pitches[0], key));
+    } else {
+        let chord: Vec<String> = event.pitches.iter().map(|p| pitch(*p, key)).collect();
+        let _ = write!(out, "<{}>", chord.join(" "));
+    }
+    out.push_str(&duration(event));
+    if event.tie_start {
+        out.push('~');
+    }
+    out.push(' ');
+}
+
+fn write_staff(out: &mut String, score: &Score, staff: usize) {
+    let (name, clef) = if staff == 0 {
+        ("upper", "treble")
+    } else {
+        ("lower", "bass")
+    };
+    let (note, accidental) = score.key.tonic;
+    let tonic = pitch_name(note, alter(accidental));
+
+    let _ = writeln!(out, "    \\new Staff = \"{}\" {{", name);
+    let _ = writeln!(out, "      \\clef {}", clef);
+    let _ = writeln!(
+        out,
+        "      \\key {} \\{}",
+        tonic,
+        mode_name(score.key.scale)
+    );
+    let _ = writeln!(out, "      \\time {}/{}", score.beats, score.beat_type);
+    if staff == 0 {
+        let _ = writeln!(out, "      \\tempo 4 = {}", score.bpm.round());
+    }
+
+    for measure in &score.measures {
+        let events = &measure.staves[staff];
+        let whole_bar = events.len() == 1 && events[0].is_rest();
+        out.push_str("      ");
+        for event in events {
+            write_event(out, score.key, event, whole_bar);
+        }
+        out.push_str("|\n");
+    }
+
+    out.push_str("    }\n");
+}
+
+// Grand staff LilyPond source for a quantised score
+pub fn to_lilypond(score: &Score) -> String {
+    let mut out = String::new();
+    out.push_str("\\version \"2.24.0\"\n\n");
+    let _ = writeln!(
+        out,
+        "\\header {{\n  title = \"{}\"\n  tagline = ##f\n}}\n",
+        score.title.replace('"', "\\\"")
+    );
+    out.push_str("\\score {\n");
+    out.push_str("  \\new PianoStaff <<\n");
+    write_staff(&mut out, score, 0);
+    write_staff(&mut out, score, 1);
+    out.push_str("  >>\n");
+    out.push_str("  \\layout { }\n");
+    out.push_str("  \\midi { }\n");
+    out.push_str("}\n");
+    out
+}
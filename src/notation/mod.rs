@@ -0,0 +1,4 @@
+pub mod abc;
+pub mod lilypond;
+pub mod musicxml;
+pub mod quantise;
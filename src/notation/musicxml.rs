@@ -0,0 +1,155 @@
+use std::fmt::Write;
+
+use crate::notation::quantise::{DIVISIONS, NoteEvent, Score};
+use crate::theory::key::{fifths, mode_name, spell, step_char};
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn type_name(base: u32) -> &'static str {
+    match base {
+        16 => "whole",
+        8 => "half",
+        4 => "quarter",
+        2 => "eighth",
+        _ => "16th",
+    }
+}
+
+fn write_attributes(out: &mut String, score: &Score) {
+    out.push_str("      <attributes>\n");
+    let _ = writeln!(out, "        <divisions>{}</divisions>", DIVISIONS);
+    let _ = writeln!(
+        out,
+        "        <key><fifths>{}</fifths><mode>{}</mode></key>",
+        fifths(score.key),
+        mode_name(score.key.scale)
+    );
+    let _ = writeln!(
+        out,
+        "        <time><beats>{}</beats><beat-type>{}</beat-type></time>",
+        score.beats, score.beat_type
+    );
+    out.push_str("        <staves>2</staves>\n");
+    out.push_str("        <clef number=\"1\"><sign>G</sign><line>2</line></clef>\n");
+    out.push_str("        <clef number=\"2\"><sign>F</sign><line>4</line></clef>\n");
+    out.push_str("      </attributes>\n");
+    let _ = writeln!(
+        out,
+        "      <direction placement=\"above\"><direction-type><metronome><beat-unit>quarter</beat-unit><per-minute>{}</per-minute></metronome></direction-type><sound tempo=\"{}\"/></direction>",
+        score.bpm.round(),
+        score.bpm.round()
+    );
+}
+
+fn write_event(out: &mut String, score: &Score, event: &NoteEvent, staff: usize, whole_bar: bool) {
+    let (base, dotted) = event.base_length();
+    let voice = staff + 1;
+
+    if event.is_rest() {
+        let rest = if whole_bar {
+            "<rest measure=\"yes\"/>"
+        } else {
+            "<rest/>"
+        };
+        let _ = writeln!(
+            out,
+            "      <note>{}<duration>{}</duration><voice>{}</voice><type>{}</type>{}<staff>{}</staff></note>",
+            rest,
+            event.duration,
+            voice,
+            type_name(base),
+            if dotted { "<dot/>" } else { "" },
+            voice
+        );
+        return;
+    }
+
+    for (index, pitch) in event.pitches.iter().enumerate() {
+        let spelled = spell(*pitch, score.key);
+        out.push_str("      <note>");
+        if index > 0 {
+            out.push_str("<chord/>");
+        }
+        out.push_str("<pitch>");
+        let _ = write!(out, "<step>{}</step>", step_char(spelled.step));
+        if spelled.alter != 0 {
+            let _ = write!(out, "<alter>{}</alter>", spelled.alter);
+        }
+        let _ = write!(out, "<octave>{}</octave></pitch>", spelled.octave);
+        let _ = write!(out, "<duration>{}</duration>", event.duration);
+        if event.tie_stop {
+            out.push_str("<tie type=\"stop\"/>");
+        }
+        if event.tie_start {
+            out.push_str("<tie type=\"start\"/>");
+        }
+        let _ = write!(
+            out,
+            "<voice>{}</voice><type>{}</type>",
+            voice,
+            type_name(base)
+        );
+        if dotted {
+            out.push_str("<dot/>");
+        }
+        let _ = write!(out, "<staff>{}</staff>", voice);
+        if event.tie_start || event.tie_stop {
+            out.push_str("<notations>");
+            if event.tie_stop {
+                out.push_str("<tied type=\"stop\"/>");
+            }
+            if event.tie_start {
+                out.push_str("<tied type=\"start\"/>");
+            }
+            out.push_str("</notations>");
+        }
+        out.push_str("</note>\n");
+    }
+}
+
+// Grand staff MusicXML (partwise) for a quantised score
+pub fn to_musicxml(score: &Score) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    out.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 3.1 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    out.push_str("<score-partwise version=\"3.1\">\n");
+    let _ = writeln!(
+        out,
+        "  <work><work-title>{}</work-title></work>",
+        escape(&score.title)
+    );
+    out.push_str(
+        "  <identification><encoding><software>rust-keys</software></encoding></identification>\n",
+    );
+    out.push_str("  <part-list><score-part id=\"P1\"><part-name>Piano</part-name></score-part></part-list>\n");
+    out.push_str("  <part id=\"P1\">\n");
+
+    let bar = score.measure_length();
+    for (number, measure) in score.measures.iter().enumerate() {
+        let _ = writeln!(out, "    <measure number=\"{}\">", number + 1);
+        if number == 0 {
+            write_attributes(&mut out, score);
+        }
+
+        for (staff, events) in measure.staves.iter().enumerate() {
+            if staff > 0 {
+                let _ = writeln!(out, "      <backup><duration>{}</duration></backup>", bar);
+            }
+            let whole_bar = events.len() == 1 && events[0].is_rest();
+            for event in events {
+                write_event(&mut out, score, event, staff, whole_bar);
+            }
+        }
+
+        out.push_str("    </measure>\n");
+    }
+
+    out.push_str("  </part>\n");
+    out.push_str("</score-partwise>\n");
+    out
+}
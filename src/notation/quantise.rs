@@ -0,0 +1,246 @@
+use musical_note::Key;
+
+use crate::types::recording::Recording;
+
+// Grid steps per quarter note, everything snaps to sixteenths
+pub const DIVISIONS: u32 = 4;
+// Notes from middle C up go on the treble staff
+pub const STAFF_SPLIT: u8 = 60;
+
+// Lengths that can be written as a single note, longest first: (steps, base steps, dotted)
+const WRITABLE: [(u32, u32, bool); 8] = [
+    (16, 16, false),
+    (12, 8, true),
+    (8, 8, false),
+    (6, 4, true),
+    (4, 4, false),
+    (3, 2, true),
+    (2, 2, false),
+    (1, 1, false),
+];
+
+#[derive(Clone, Debug)]
+pub struct NoteEvent {
+    pub pitches: Vec<u8>, // empty for a rest
+    pub duration: u32,    // grid steps, always a writable length
+    pub tie_start: bool,
+    pub tie_stop: bool,
+}
+
+impl NoteEvent {
+    pub fn is_rest(&self) -> bool {
+        self.pitches.is_empty()
+    }
+
+    // (undotted length in steps, dotted)
+    pub fn base_length(&self) -> (u32, bool) {
+        WRITABLE
+            .iter()
+            .find(|(steps, _, _)| *steps == self.duration)
+            .map(|(_, base, dotted)| (*base, *dotted))
+            .unwrap_or((1, false))
+    }
+}
+
+pub struct Measure {
+    pub staves: [Vec<NoteEvent>; 2], // treble, bass
+}
+
+pub struct Score {
+    pub title: String,
+    pub key: Key,
+    pub bpm: f32,
+    pub beats: u8,
+    pub beat_type: u8,
+    pub measures: Vec<Measure>,
+}
+
+impl Score {
+    // Grid steps in one measure
+    pub fn measure_length(&self) -> u32 {
+        measure_length(self.beats, self.beat_type)
+    }
+}
+
+fn measure_length(beats: u8, beat_type: u8) -> u32 {
+    (beats as u32 * DIVISIONS * 4 / beat_type.max(1) as u32).max(1)
+}
+
+// (start step, end step, note)
+fn note_spans(recording: &Recording) -> Vec<(u32, u32, u8)> {
+    let step_micros = 60_000_000.0 / recording.bpm.max(1.0) as f64 / DIVISIONS as f64;
+    let to_step = |micros: u64| (micros as f64 / step_micros).round() as u32;
+    let messages = &recording.messages;
+    let start = messages.first().map(|(t, _)| *t).unwrap_or(0);
+    let mut spans = Vec::new();
+
+    for (i, (t, [status, note, velocity])) in messages.iter().enumerate() {
+        if !(0x90..=0x9f).contains(status) || *velocity == 0 {
+            continue;
+        }
+
+        let end = messages[i + 1..]
+            .iter()
+            .find(|(_, [s, n, v])| {
+                n == note && ((0x80..=0x8f).contains(s) || ((0x90..=0x9f).contains(s) && *v == 0))
+            })
+            .map(|(end, _)| *end)
+            .unwrap_or(*t);
+
+        let start_step = to_step(t - start);
+        let end_step = to_step(end - start).max(start_step + 1);
+        spans.push((start_step, end_step, *note));
+    }
+
+    spans
+}
+
+// Reduce a staff to one voice: notes starting together form a chord
+// and a chord is cut short when the next one starts
+fn staff_voice(spans: &[(u32, u32, u8)]) -> Vec<(u32, u32, Vec<u8>)> {
+    let mut chords: Vec<(u32, u32, Vec<u8>)> = Vec::new();
+
+    let mut sorted = spans.to_vec();
+    sorted.sort();
+    for (start, end, note) in sorted {
+        match chords.last_mut() {
+            Some((chord_start, chord_end, pitches)) if *chord_start == start => {
+                *chord_end = (*chord_end).max(end);
+                if !pitches.contains(&note) {
+                    pitches.push(note);
+                }
+            }
+            _ => chords.push((start, end, vec![note])),
+        }
+    }
+
+    for i in 1..chords.len() {
+        let next_start = chords[i].0;
+        let previous = &mut chords[i - 1];
+        previous.1 = previous.1.min(next_start);
+    }
+
+    chords
+}
+
+// Split a span at bar lines and into writable lengths, tying the pieces
+fn push_span(
+    measures: &mut [Measure],
+    staff: usize,
+    start: u32,
+    end: u32,
+    pitches: &[u8],
+    bar: u32,
+) {
+    let mut position = start;
+    while position < end {
+        let bar_end = (position / bar + 1) * bar;
+        let remaining = end.min(bar_end) - position;
+        let (steps, _, _) = WRITABLE
+            .iter()
+            .find(|(steps, _, _)| *steps <= remaining)
+            .copied()
+            .unwrap_or((1, 1, false));
+
+        let is_rest = pitches.is_empty();
+        measures[(position / bar) as usize].staves[staff].push(NoteEvent {
+            pitches: pitches.to_vec(),
+            duration: steps,
+            tie_start: !is_rest && position + steps < end,
+            tie_stop: !is_rest && position > start,
+        });
+        position += steps;
+    }
+}
+
+pub fn quantise(recording: &Recording, key: Key) -> Score {
+    let (beats, beat_type) = recording.time_signature;
+    let bar = measure_length(beats, beat_type);
+    let spans = note_spans(recording);
+
+    let voices = [
+        staff_voice(
+            &spans
+                .iter()
+                .copied()
+                .filter(|(_, _, n)| *n >= STAFF_SPLIT)
+                .collect::<Vec<_>>(),
+        ),
+        staff_voice(
+            &spans
+                .iter()
+                .copied()
+                .filter(|(_, _, n)| *n < STAFF_SPLIT)
+                .collect::<Vec<_>>(),
+        ),
+    ];
+
+    let last_step = spans.iter().map(|(_, end, _)| *end).max().unwrap_or(0);
+    let measure_count = last_step.div_ceil(bar).max(1);
+    let mut measures: Vec<Measure> = (0..measure_count)
+        .map(|_| Measure {
+            staves: [Vec::new(), Vec::new()],
+        })
+        .collect();
+
+    for (staff, voice) in voices.iter().enumerate() {
+        let mut cursor = 0;
+        for (start, end, pitches) in voice {
+            if *start > cursor {
+                push_span(&mut measures, staff, cursor, *start, &[], bar);
+            }
+            push_span(&mut measures, staff, *start, *end, pitches, bar);
+            cursor = *end;
+        }
+        push_span(&mut measures, staff, cursor, measure_count * bar, &[], bar);
+    }
+
+    Score {
+        title: recording.title.clone(),
+        key,
+        bpm: recording.bpm,
+        beats,
+        beat_type,
+        measures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(messages: &[(u64, [u8; 3])]) -> Recording {
+        let mut recording = Recording::new("test");
+        for message in messages {
+            recording.push(*message);
+        }
+        recording
+    }
+
+    #[test]
+    fn test_ties_across_bar_lines() {
+        // at 120 bpm a quarter is 500ms, the C starts on beat 4 and lasts two beats
+        let score = quantise(
+            &recording(&[
+                (0, [0x90, 67, 64]),
+                (1_500_000, [0x90, 60, 64]),
+                (2_500_000, [0x80, 60, 0]),
+                (2_500_000, [0x80, 67, 0]),
+            ]),
+            Key::default(),
+        );
+
+        assert_eq!(score.measures.len(), 2);
+        let first = &score.measures[0].staves[0];
+        let second = &score.measures[1].staves[0];
+
+        // G is cut short by the C starting on beat 4, C is tied over the bar
+        assert_eq!(first[0].pitches, vec![67]);
+        assert_eq!(first[0].duration, 12);
+        assert!(first[1].tie_start && !first[1].tie_stop);
+        assert!(second[0].tie_stop && !second[0].tie_start);
+        assert_eq!(second[0].duration, 4);
+        assert!(second[1].is_rest());
+        assert!(score.measures[0].staves[1][0].is_rest());
+    }
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Stage;
+use crate::types::midi::Message;
+
+// MIDI clock sends this many ticks per beat
+const CLOCK_PPQN: u32 = 24;
+pub const CLOCK: u8 = 0xf8;
+const CLOCK_START: u8 = 0xfa;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArpPattern {
+    Up,
+    Down,
+    UpDown,
+    Random,
+    AsPlayed,
+}
+
+impl ArpPattern {
+    pub const ALL: [ArpPattern; 5] = [
+        ArpPattern::Up,
+        ArpPattern::Down,
+        ArpPattern::UpDown,
+        ArpPattern::Random,
+        ArpPattern::AsPlayed,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ArpPattern::Up => "up",
+            ArpPattern::Down => "down",
+            ArpPattern::UpDown => "up-down",
+            ArpPattern::Random => "random",
+            ArpPattern::AsPlayed => "as played",
+        }
+    }
+}
+
+// Where the beat comes from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArpSync {
+    Tempo(u16), // beats per minute
+    MidiClock,  // 24 ticks per beat from the keyboard or another device
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArpSettings {
+    pub enabled: bool,
+    pub pattern: ArpPattern,
+    pub octaves: u8,        // 1 - 4
+    pub steps_per_beat: u8, // 1 quarters, 2 eighths, 3 triplets, 4 sixteenths
+    pub gate: f32,          // share of a step each note sounds, 0.1 - 1.0
+    pub sync: ArpSync,
+    pub midi_out: Option<String>, // output port also sent the arpeggio
+}
+
+impl Default for ArpSettings {
+    fn default() -> Self {
+        ArpSettings {
+            enabled: false,
+            pattern: ArpPattern::Up,
+            octaves: 1,
+            steps_per_beat: 2,
+            gate: 0.5,
+            sync: ArpSync::Tempo(120),
+            midi_out: None,
+        }
+    }
+}
+
+impl ArpSettings {
+    pub fn label(&self) -> String {
+        let sync = match self.sync {
+            ArpSync::Tempo(bpm) => format!("{} bpm", bpm),
+            ArpSync::MidiClock => "MIDI clock".to_string(),
+        };
+        format!(
+            "{}, {} oct, 1/{} beat, gate {:.0}%, {}",
+            self.pattern.label(),
+            self.octaves,
+            self.steps_per_beat,
+            self.gate * 100.0,
+            sync
+        )
+    }
+}
+
+// Turns held keys into a repeating pattern. Notes are swallowed and replayed on each
+// step, so it has to be ticked between messages, see `spawn_clocked`.
+pub struct Arpeggiator {
+    settings: ArpSettings,
+    held: Vec<(u8, u8)>, // key and velocity, in the order played
+    channel: u8,
+    step: usize,
+    next_step: Option<u64>, // when running on tempo
+    ticks: u32,             // MIDI clock ticks since the last step
+    last_clock: Option<u64>,
+    clock_interval: u64,         // between MIDI clock ticks, for gate lengths
+    sounding: Option<(u8, u64)>, // note and when it ends
+    seed: u64,
+}
+
+impl Arpeggiator {
+    pub fn new(settings: ArpSettings) -> Self {
+        Arpeggiator {
+            settings,
+            held: Vec::new(),
+            channel: 0,
+            step: 0,
+            next_step: None,
+            ticks: 0,
+            last_clock: None,
+            // 120 bpm until the clock has been heard twice
+            clock_interval: 60_000_000 / 120 / CLOCK_PPQN as u64,
+            sounding: None,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    fn step_length(&self) -> u64 {
+        let steps = self.settings.steps_per_beat.max(1) as u64;
+        match self.settings.sync {
+            ArpSync::Tempo(bpm) => 60_000_000 / bpm.max(1) as u64 / steps,
+            ArpSync::MidiClock => self.clock_interval * (CLOCK_PPQN as u64 / steps),
+        }
+    }
+
+    // xorshift, good enough to pick notes
+    fn random(&mut self) -> usize {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed as usize
+    }
+
+    // The notes one cycle of the pattern plays, with their velocities
+    fn sequence(&self) -> Vec<(u8, u8)> {
+        let mut keys = self.held.clone();
+        if self.settings.pattern != ArpPattern::AsPlayed {
+            keys.sort();
+        }
+        let mut notes: Vec<(u8, u8)> = (0..self.settings.octaves.max(1))
+            .flat_map(|octave| {
+                keys.iter().filter_map(move |&(key, velocity)| {
+                    let note = key as u16 + 12 * octave as u16;
+                    (note < 128).then_some((note as u8, velocity))
+                })
+            })
+            .collect();
+
+        match self.settings.pattern {
+            ArpPattern::Down => notes.reverse(),
+            // the top and bottom notes are not repeated on the turn
+            ArpPattern::UpDown if notes.len() > 2 => {
+                let down: Vec<(u8, u8)> = notes[1..notes.len() - 1].iter().rev().copied().collect();
+                notes.extend(down);
+            }
+            _ => (),
+        }
+        notes
+    }
+
+    fn release(&mut self, time: u64, out: &mut Vec<Message>) {
+        if let Some((note, _)) = self.sounding.take() {
+            out.push((time, [0x80 | self.channel, note, 0]));
+        }
+    }
+
+    fn play_step(&mut self, time: u64, out: &mut Vec<Message>) {
+        self.release(time, out);
+        let sequence = self.sequence();
+        if sequence.is_empty() {
+            return;
+        }
+        let index = match self.settings.pattern {
+            ArpPattern::Random => self.random() % sequence.len(),
+            _ => self.step % sequence.len(),
+        };
+        self.step += 1;
+
+        let (note, velocity) = sequence[index];
+        let gate = (self.step_length() as f32 * self.settings.gate.clamp(0.1, 1.0)) as u64;
+        out.push((time, [0x90 | self.channel, note, velocity]));
+        self.sounding = Some((note, time + gate.max(1)));
+    }
+}
+
+impl Stage for Arpeggiator {
+    fn process(&mut self, (time, [status, data1, data2]): Message, out: &mut Vec<Message>) {
+        match status & 0xf0 {
+            0x90 if data2 > 0 => {
+                // the first key starts the pattern from the top
+                if self.held.is_empty() {
+                    self.step = 0;
+                    self.ticks = 0;
+                    if let ArpSync::Tempo(_) = self.settings.sync {
+                        self.next_step = Some(time);
+                    }
+                }
+                self.held.retain(|(key, _)| *key != data1);
+                self.held.push((data1, data2));
+                self.channel = status & 0x0f;
+            }
+            0x80 | 0x90 => {
+                self.held.retain(|(key, _)| *key != data1);
+                if self.held.is_empty() {
+                    self.next_step = None;
+                    self.release(time, out);
+                }
+            }
+            0xa0 => (),
+            0xf0 => match status {
+                CLOCK => {
+                    if let Some(last) = self.last_clock {
+                        self.clock_interval = time.saturating_sub(last);
+                    }
+                    self.last_clock = Some(time);
+                    if self.settings.sync != ArpSync::MidiClock || self.held.is_empty() {
+                        return;
+                    }
+                    if self.ticks == 0 {
+                        self.play_step(time, out);
+                    }
+                    let per_step = CLOCK_PPQN / self.settings.steps_per_beat.max(1) as u32;
+                    self.ticks = (self.ticks + 1) % per_step.max(1);
+                }
+                CLOCK_START => self.ticks = 0,
+                _ => (),
+            },
+            _ => out.push((time, [status, data1, data2])),
+        }
+    }
+
+    fn tick(&mut self, now: u64, out: &mut Vec<Message>) {
+        // catch up in time order, ending notes before the steps after them
+        loop {
+            let next = self.next_step.filter(|next| *next <= now);
+            match (self.sounding, next) {
+                (Some((_, end)), next) if end <= now && next.is_none_or(|next| end <= next) => {
+                    self.release(end, out);
+                }
+                (_, Some(next)) => {
+                    self.play_step(next, out);
+                    self.next_step = Some(next + self.step_length().max(1));
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes_on(out: &[Message]) -> Vec<u8> {
+        out.iter()
+            .filter(|(_, [status, _, _])| status & 0xf0 == 0x90)
+            .map(|(_, [_, note, _])| *note)
+            .collect()
+    }
+
+    #[test]
+    fn test_patterns_and_timing() {
+        // eighths at 120 bpm are 250 ms apart
+        let mut arp = Arpeggiator::new(ArpSettings {
+            enabled: true,
+            pattern: ArpPattern::UpDown,
+            octaves: 2,
+            ..ArpSettings::default()
+        });
+        let mut out = Vec::new();
+        arp.process((0, [0x90, 64, 100]), &mut out);
+        arp.process((0, [0x90, 60, 90]), &mut out);
+        assert!(out.is_empty());
+        arp.tick(1_500_000, &mut out);
+        assert_eq!(notes_on(&out), vec![60, 64, 72, 76, 72, 64, 60]);
+
+        // with a gate of a half, each note ends 125 ms after it starts
+        let last = out
+            .iter()
+            .rev()
+            .find(|(_, [s, _, _])| *s == 0x90)
+            .unwrap()
+            .0;
+        out.clear();
+        arp.tick(1_500_000 + 125_000, &mut out);
+        assert_eq!(out, vec![(last + 125_000, [0x80, 60, 0])]);
+
+        // releasing every key stops it
+        arp.process((1_600_000, [0x80, 60, 0]), &mut out);
+        arp.process((1_600_000, [0x80, 64, 0]), &mut out);
+        out.clear();
+        arp.tick(3_000_000, &mut out);
+        assert!(out.is_empty());
+
+        // on MIDI clock, sixteenths step every 6 ticks in the order played
+        let mut arp = Arpeggiator::new(ArpSettings {
+            pattern: ArpPattern::AsPlayed,
+            steps_per_beat: 4,
+            sync: ArpSync::MidiClock,
+            ..ArpSettings::default()
+        });
+        let mut out = Vec::new();
+        arp.process((0, [0x91, 67, 100]), &mut out);
+        arp.process((0, [0x91, 60, 100]), &mut out);
+        for tick in 0..12 {
+            arp.process((tick * 20_000, [CLOCK, 0, 0]), &mut out);
+        }
+        assert_eq!(notes_on(&out), vec![67, 60]);
+        assert_eq!(out[0].1[0], 0x91);
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use musical_note::Key;
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Stage;
+use crate::theory::key::{key_name, parse_key, scale_pitch_classes};
+use crate::types::midi::Message;
+
+// Harmony sits below the played note, which stays on top as the melody
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Voicing {
+    Thirds, // a third below
+    Sixths, // a sixth below
+    Block,  // a third and a fifth below, a triad under the melody
+}
+
+impl Voicing {
+    pub const ALL: [Voicing; 3] = [Voicing::Thirds, Voicing::Sixths, Voicing::Block];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Voicing::Thirds => "thirds",
+            Voicing::Sixths => "sixths",
+            Voicing::Block => "block chords",
+        }
+    }
+
+    // Scale steps from the melody to each added note
+    fn steps(self) -> &'static [i32] {
+        match self {
+            Voicing::Thirds => &[-2],
+            Voicing::Sixths => &[-5],
+            Voicing::Block => &[-2, -4],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Harmony {
+    Chord(Vec<u8>), // semitones above the played note, which takes the lowest place
+    Diatonic { key: String, voicing: Voicing },
+}
+
+impl Harmony {
+    pub fn label(&self) -> String {
+        match self {
+            Harmony::Chord(shape) => {
+                let shape: Vec<String> = shape.iter().map(|s| s.to_string()).collect();
+                format!("chord memory {}", shape.join("-"))
+            }
+            Harmony::Diatonic { key, voicing } => format!("{} in {}", voicing.label(), key),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HarmonySettings {
+    pub enabled: bool,
+    pub harmony: Harmony,
+}
+
+impl Default for HarmonySettings {
+    fn default() -> Self {
+        HarmonySettings {
+            enabled: false,
+            harmony: Harmony::Diatonic {
+                key: "C major".to_string(),
+                voicing: Voicing::Thirds,
+            },
+        }
+    }
+}
+
+// Which notes were last started by the harmoniser rather than a key, so the UI can
+// tell them apart. Atomics, as it is written from the MIDI callback.
+#[derive(Clone)]
+pub struct GeneratedNotes {
+    notes: Arc<[AtomicBool; 128]>,
+}
+
+impl GeneratedNotes {
+    pub fn new() -> Self {
+        GeneratedNotes {
+            notes: Arc::new([const { AtomicBool::new(false) }; 128]),
+        }
+    }
+
+    pub fn is_generated(&self, note: u8) -> bool {
+        self.notes[(note & 0x7f) as usize].load(Ordering::Relaxed)
+    }
+
+    fn mark(&self, note: u8, generated: bool) {
+        self.notes[(note & 0x7f) as usize].store(generated, Ordering::Relaxed);
+    }
+}
+
+// Every note of the key's scale across the MIDI range, lowest first
+fn scale_notes(key: Key) -> Vec<u8> {
+    let classes = scale_pitch_classes(key);
+    (0..128u8)
+        .filter(|note| classes.contains(&(note % 12)))
+        .collect()
+}
+
+// Adds notes to each key played, from a stored shape or the scale
+pub struct HarmonyStage {
+    harmony: Harmony,
+    scale: Vec<u8>,
+    generated: GeneratedNotes,
+    added: HashMap<(u8, u8), Vec<u8>>, // by channel and key, so releases match
+    counts: [u8; 128],                 // keys each added note is sounding for
+    held: [bool; 128],
+}
+
+impl HarmonyStage {
+    pub fn new(settings: &HarmonySettings, generated: GeneratedNotes) -> Self {
+        let scale = match &settings.harmony {
+            Harmony::Diatonic { key, .. } => {
+                let key = parse_key(key).unwrap_or_else(|| {
+                    let c_major = parse_key("C").unwrap();
+                    println!("Unknown key {}, harmonising in {}.", key, key_name(c_major));
+                    c_major
+                });
+                scale_notes(key)
+            }
+            Harmony::Chord(_) => Vec::new(),
+        };
+        HarmonyStage {
+            harmony: settings.harmony.clone(),
+            scale,
+            generated,
+            added: HashMap::new(),
+            counts: [0; 128],
+            held: [false; 128],
+        }
+    }
+
+    // The notes to add to a key. Keys outside the scale sound alone.
+    fn harmonise(&self, note: u8) -> Vec<u8> {
+        let notes: Vec<u8> = match &self.harmony {
+            Harmony::Chord(shape) => shape
+                .iter()
+                .filter_map(|interval| note.checked_add(*interval))
+                .filter(|added| *added < 128)
+                .collect(),
+            Harmony::Diatonic { voicing, .. } => match self.scale.binary_search(&note) {
+                Ok(index) => voicing
+                    .steps()
+                    .iter()
+                    .filter_map(|step| usize::try_from(index as i32 + step).ok())
+                    .filter_map(|index| self.scale.get(index).copied())
+                    .collect(),
+                Err(_) => Vec::new(),
+            },
+        };
+        notes.into_iter().filter(|added| *added != note).collect()
+    }
+}
+
+impl Stage for HarmonyStage {
+    fn process(&mut self, (time, [status, note, velocity]): Message, out: &mut Vec<Message>) {
+        let channel = status & 0x0f;
+        let note = note & 0x7f;
+        match status & 0xf0 {
+            0x90 if velocity > 0 => {
+                self.held[note as usize] = true;
+                self.generated.mark(note, false);
+                out.push((time, [status, note, velocity]));
+
+                let added = self.harmonise(note);
+                for &extra in &added {
+                    // a note already sounding is not struck again
+                    if self.counts[extra as usize] == 0 && !self.held[extra as usize] {
+                        self.generated.mark(extra, true);
+                        out.push((time, [status, extra, velocity]));
+                    }
+                    self.counts[extra as usize] += 1;
+                }
+                self.added.insert((channel, note), added);
+            }
+            0x80 | 0x90 => {
+                self.held[note as usize] = false;
+                // still sounding as part of another key's harmony
+                if self.counts[note as usize] == 0 {
+                    out.push((time, [status, note, velocity]));
+                }
+                for extra in self.added.remove(&(channel, note)).unwrap_or_default() {
+                    let count = &mut self.counts[extra as usize];
+                    *count = count.saturating_sub(1);
+                    if *count == 0 && !self.held[extra as usize] {
+                        out.push((time, [0x80 | channel, extra, 0]));
+                    }
+                }
+            }
+            _ => out.push((time, [status, note, velocity])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chord_memory_and_diatonic() {
+        let settings = HarmonySettings {
+            enabled: true,
+            harmony: Harmony::Chord(vec![0, 3, 7]),
+        };
+        let generated = GeneratedNotes::new();
+        let mut stage = HarmonyStage::new(&settings, generated.clone());
+        let mut out = Vec::new();
+        stage.process((0, [0x90, 62, 80]), &mut out);
+        assert_eq!(
+            out,
+            vec![
+                (0, [0x90, 62, 80]),
+                (0, [0x90, 65, 80]),
+                (0, [0x90, 69, 80])
+            ]
+        );
+        assert!(generated.is_generated(65) && !generated.is_generated(62));
+
+        // a shared note keeps sounding until both keys are up
+        out.clear();
+        stage.process((1, [0x90, 65, 80]), &mut out);
+        stage.process((2, [0x80, 62, 0]), &mut out);
+        assert!(!out.contains(&(2, [0x80, 65, 0])));
+        out.clear();
+        stage.process((3, [0x80, 65, 0]), &mut out);
+        assert!(out.contains(&(3, [0x80, 65, 0])));
+
+        // block chords in G major put the triad under the melody, F# included
+        let settings = HarmonySettings {
+            enabled: true,
+            harmony: Harmony::Diatonic {
+                key: "G".to_string(),
+                voicing: Voicing::Block,
+            },
+        };
+        let mut stage = HarmonyStage::new(&settings, GeneratedNotes::new());
+        assert_eq!(stage.harmonise(74), vec![71, 67]); // D over B and G
+        assert_eq!(stage.harmonise(69), vec![66, 62]); // A over F# and D
+        assert!(stage.harmonise(65).is_empty()); // F natural is not in the key
+        out.clear();
+        stage.process((0, [0xb0, 64, 127]), &mut out);
+        assert_eq!(out, vec![(0, [0xb0, 64, 127])]);
+    }
+}
@@ -0,0 +1,76 @@
+pub mod arp;
+pub mod harmony;
+pub mod transpose;
+pub mod velocity;
+pub mod zones;
+
+use std::{
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::types::midi::Message;
+
+// How often stages that play on their own are ticked
+const TICK: Duration = Duration::from_millis(1);
+
+// A step between the keyboard and everything that listens to it. Stages run in the
+// MIDI callback, so they must not block.
+pub trait Stage: Send {
+    // Push the messages to pass on in place of this one, none to drop it
+    fn process(&mut self, message: Message, out: &mut Vec<Message>);
+
+    // Called between messages by `spawn_clocked` for stages that play over time
+    fn tick(&mut self, _now: u64, _out: &mut Vec<Message>) {}
+}
+
+fn run_stages(stages: &mut [Box<dyn Stage>], mut messages: Vec<Message>) -> Vec<Message> {
+    for stage in stages {
+        let mut out = Vec::with_capacity(messages.len());
+        for message in messages {
+            stage.process(message, &mut out);
+        }
+        messages = out;
+    }
+    messages
+}
+
+// Runs stages that need ticking, like the arpeggiator, on a thread of their own and
+// sends what they play to the targets. Messages are restamped with the thread's
+// clock so they line up with the ticks.
+pub fn spawn_clocked(
+    mut stages: Vec<Box<dyn Stage>>,
+    targets: Vec<Sender<Message>>,
+) -> Sender<Message> {
+    let (tx, rx): (Sender<Message>, Receiver<Message>) = channel();
+    let start = Instant::now();
+
+    thread::spawn(move || {
+        loop {
+            let now = || start.elapsed().as_micros() as u64;
+            let mut messages = match rx.recv_timeout(TICK) {
+                Ok((_, data)) => run_stages(&mut stages, vec![(now(), data)]),
+                Err(RecvTimeoutError::Timeout) => Vec::new(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            // what a stage plays on a tick still goes through the stages after it
+            let time = now();
+            for index in 0..stages.len() {
+                let mut out = Vec::new();
+                stages[index].tick(time, &mut out);
+                if !out.is_empty() {
+                    messages.extend(run_stages(&mut stages[index + 1..], out));
+                }
+            }
+
+            for message in messages {
+                for target in &targets {
+                    target.send(message).ok();
+                }
+            }
+        }
+    });
+    tx
+}
@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Stage;
+use crate::types::midi::Message;
+
+pub const MAX_SEMITONES: i8 = 24;
+pub const MAX_OCTAVES: i8 = 3;
+
+// Applied to everything played, so the same fingering sounds in another key
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transpose {
+    pub semitones: i8, // -24 - 24
+    pub octaves: i8,   // -3 - 3
+}
+
+impl Transpose {
+    pub fn offset(self) -> i32 {
+        self.semitones as i32 + 12 * self.octaves as i32
+    }
+
+    pub fn shift_semitones(&mut self, by: i8) {
+        self.semitones = (self.semitones + by).clamp(-MAX_SEMITONES, MAX_SEMITONES);
+    }
+
+    pub fn shift_octaves(&mut self, by: i8) {
+        self.octaves = (self.octaves + by).clamp(-MAX_OCTAVES, MAX_OCTAVES);
+    }
+
+    // The note that sounds for a key, None when it falls off the MIDI range
+    pub fn apply(self, note: u8) -> Option<u8> {
+        u8::try_from(note as i32 + self.offset())
+            .ok()
+            .filter(|note| *note < 128)
+    }
+
+    pub fn label(self) -> String {
+        match (self.semitones, self.octaves) {
+            (0, 0) => "no transpose".to_string(),
+            (semitones, 0) => format!("{:+} st", semitones),
+            (0, octaves) => format!("{:+} oct", octaves),
+            (semitones, octaves) => format!("{:+} st {:+} oct", semitones, octaves),
+        }
+    }
+}
+
+// Shared between the UI, which changes it, and the stage in the MIDI callback
+#[derive(Clone)]
+pub struct TransposeHandle {
+    transpose: Arc<Mutex<Transpose>>,
+}
+
+impl TransposeHandle {
+    pub fn new(transpose: Transpose) -> Self {
+        TransposeHandle {
+            transpose: Arc::new(Mutex::new(transpose)),
+        }
+    }
+
+    pub fn get(&self) -> Transpose {
+        self.transpose.lock().map(|t| *t).unwrap_or_default()
+    }
+
+    pub fn modify(&self, change: impl FnOnce(&mut Transpose)) {
+        if let Ok(mut transpose) = self.transpose.lock() {
+            change(&mut transpose);
+        }
+    }
+}
+
+pub struct TransposeStage {
+    handle: TransposeHandle,
+    current: Transpose,
+    // The note each held key sounds, by channel and key, so releases still match
+    // after the transpose changes
+    sounding: [[Option<u8>; 128]; 16],
+}
+
+impl TransposeStage {
+    pub fn new(handle: TransposeHandle) -> Self {
+        TransposeStage {
+            current: handle.get(),
+            handle,
+            sounding: [[None; 128]; 16],
+        }
+    }
+}
+
+impl Stage for TransposeStage {
+    fn process(&mut self, (time, [status, note, velocity]): Message, out: &mut Vec<Message>) {
+        // never wait on the UI from the MIDI callback
+        if let Ok(transpose) = self.handle.transpose.try_lock() {
+            self.current = *transpose;
+        }
+
+        let held = &mut self.sounding[(status & 0x0f) as usize][(note & 0x7f) as usize];
+        let sounding = match status & 0xf0 {
+            0x90 if velocity > 0 => {
+                *held = self.current.apply(note);
+                *held
+            }
+            0x80 | 0x90 => held.take(),
+            0xa0 => *held,
+            _ => Some(note),
+        };
+        if let Some(note) = sounding {
+            out.push((time, [status, note, velocity]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_stage() {
+        let handle = TransposeHandle::new(Transpose::default());
+        handle.modify(|t| {
+            t.shift_semitones(30);
+            t.shift_octaves(-1);
+        });
+        assert_eq!(handle.get().offset(), 12);
+
+        let mut stage = TransposeStage::new(handle.clone());
+        let mut out = Vec::new();
+        stage.process((0, [0x90, 60, 100]), &mut out);
+        // changing the transpose while the key is down still releases what sounded
+        handle.modify(|t| t.shift_octaves(1));
+        stage.process((1, [0x80, 60, 0]), &mut out);
+        stage.process((2, [0x90, 60, 100]), &mut out);
+        // beyond the top of the range is dropped, along with its release
+        stage.process((3, [0x90, 120, 100]), &mut out);
+        stage.process((4, [0x90, 120, 0]), &mut out);
+        stage.process((5, [0xb0, 64, 127]), &mut out);
+
+        assert_eq!(
+            out,
+            vec![
+                (0, [0x90, 72, 100]),
+                (1, [0x80, 72, 0]),
+                (2, [0x90, 84, 100]),
+                (5, [0xb0, 64, 127]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Stage;
+use crate::types::midi::Message;
+
+// Played softly and loudly, calibration maps these to the targets below
+pub const CALIBRATION_NOTES: usize = 4;
+const SOFT_TARGET: u8 = 32;
+const LOUD_TARGET: u8 = 108;
+
+// Exponents of out = in^gamma on the 0.0 - 1.0 scale
+const SOFT_GAMMA: f32 = 0.6;
+const HARD_GAMMA: f32 = 1.6;
+
+// How key velocity maps to the velocity everything else sees
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum VelocityCurve {
+    #[default]
+    Linear,
+    Soft,                  // louder for a light touch
+    Hard,                  // needs more force for the same level
+    Fixed(u8),             // every note the same
+    Custom(Vec<(u8, u8)>), // (in, out) points joined by straight lines, sorted by in
+}
+
+fn gamma(velocity: u8, gamma: f32) -> u8 {
+    (127.0 * (velocity as f32 / 127.0).powf(gamma)).round() as u8
+}
+
+fn interpolate(points: &[(u8, u8)], velocity: u8) -> u8 {
+    let Some(&(first_in, first_out)) = points.first() else {
+        return velocity;
+    };
+    if velocity <= first_in {
+        return first_out;
+    }
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if velocity <= x1 {
+            let t = velocity.saturating_sub(x0) as f32 / x1.saturating_sub(x0).max(1) as f32;
+            return (y0 as f32 + (y1 as f32 - y0 as f32) * t).round() as u8;
+        }
+    }
+    points.last().map(|(_, out)| *out).unwrap_or(velocity)
+}
+
+impl VelocityCurve {
+    // Note-offs sent as a note-on with velocity 0 stay that way
+    pub fn apply(&self, velocity: u8) -> u8 {
+        if velocity == 0 {
+            return 0;
+        }
+        let mapped = match self {
+            VelocityCurve::Linear => velocity,
+            VelocityCurve::Soft => gamma(velocity, SOFT_GAMMA),
+            VelocityCurve::Hard => gamma(velocity, HARD_GAMMA),
+            VelocityCurve::Fixed(fixed) => *fixed,
+            VelocityCurve::Custom(points) => interpolate(points, velocity),
+        };
+        mapped.clamp(1, 127)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            VelocityCurve::Linear => "linear".to_string(),
+            VelocityCurve::Soft => "soft".to_string(),
+            VelocityCurve::Hard => "hard".to_string(),
+            VelocityCurve::Fixed(fixed) => format!("fixed {}", fixed),
+            VelocityCurve::Custom(points) => format!("custom, {} points", points.len()),
+        }
+    }
+
+    // The next kind of curve, for stepping through them in the editor
+    pub fn next_kind(&self) -> VelocityCurve {
+        match self {
+            VelocityCurve::Linear => VelocityCurve::Soft,
+            VelocityCurve::Soft => VelocityCurve::Hard,
+            VelocityCurve::Hard => VelocityCurve::Fixed(100),
+            VelocityCurve::Fixed(_) => {
+                VelocityCurve::Custom([0, 32, 64, 96, 127].iter().map(|&v| (v, v)).collect())
+            }
+            VelocityCurve::Custom(_) => VelocityCurve::Linear,
+        }
+    }
+}
+
+fn average(velocities: &[u8]) -> u8 {
+    let sum: u32 = velocities.iter().map(|v| *v as u32).sum();
+    (sum / velocities.len().max(1) as u32) as u8
+}
+
+// A custom curve that brings a player's soft and loud notes to comfortable levels
+pub fn suggest_curve(soft: &[u8], loud: &[u8]) -> Result<VelocityCurve, String> {
+    let (soft, loud) = (average(soft), average(loud));
+    if soft == 0 || loud < soft.saturating_add(8) {
+        return Err(format!(
+            "Soft and loud notes are too close ({} and {}), try a bigger difference",
+            soft, loud
+        ));
+    }
+
+    let mut points = vec![(0, 0), (soft, SOFT_TARGET), (loud, LOUD_TARGET)];
+    if loud < 127 {
+        points.push((127, 127));
+    }
+    Ok(VelocityCurve::Custom(points))
+}
+
+pub struct VelocityStage {
+    curve: VelocityCurve,
+}
+
+impl VelocityStage {
+    pub fn new(curve: VelocityCurve) -> Self {
+        VelocityStage { curve }
+    }
+}
+
+impl Stage for VelocityStage {
+    fn process(&mut self, (time, [status, note, velocity]): Message, out: &mut Vec<Message>) {
+        let velocity = match status & 0xf0 {
+            0x90 => self.curve.apply(velocity),
+            _ => velocity,
+        };
+        out.push((time, [status, note, velocity]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves() {
+        for curve in [
+            VelocityCurve::Soft,
+            VelocityCurve::Hard,
+            VelocityCurve::Fixed(90),
+        ] {
+            assert_eq!(curve.apply(0), 0);
+        }
+        assert!(VelocityCurve::Soft.apply(40) > 40);
+        assert!(VelocityCurve::Hard.apply(40) < 40);
+        assert_eq!(VelocityCurve::Hard.apply(127), 127);
+        assert_eq!(VelocityCurve::Fixed(90).apply(10), 90);
+
+        let custom = VelocityCurve::Custom(vec![(0, 0), (20, 60), (100, 100)]);
+        assert_eq!(custom.apply(10), 30);
+        assert_eq!(custom.apply(60), 80);
+        assert_eq!(custom.apply(120), 100);
+
+        let suggested = suggest_curve(&[10, 14, 12, 12], &[70, 74, 72, 72]).unwrap();
+        assert_eq!(suggested.apply(12), SOFT_TARGET);
+        assert_eq!(suggested.apply(72), LOUD_TARGET);
+        assert!(suggest_curve(&[50, 50], &[52, 54]).is_err());
+    }
+}
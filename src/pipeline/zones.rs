@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::Stage;
+use crate::theory::key::note_name;
+use crate::types::midi::Message;
+
+// Bank select, sent ahead of a program change
+const BANK_SELECT: u8 = 0;
+
+// A key range played through its own preset and channel. Zones may overlap to layer
+// sounds, or sit side by side for a split.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    pub low: u8,     // lowest key, inclusive
+    pub high: u8,    // highest key, inclusive
+    pub channel: u8, // 0 - 15
+    pub bank: u8,
+    pub program: u8,
+    pub transpose: i8, // on top of the global transpose
+}
+
+impl Zone {
+    pub fn contains(&self, key: u8) -> bool {
+        (self.low..=self.high).contains(&key)
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "{} {}-{} ch {} preset {}:{}{}",
+            self.name,
+            note_name(self.low),
+            note_name(self.high),
+            self.channel + 1,
+            self.bank,
+            self.program,
+            match self.transpose {
+                0 => String::new(),
+                semitones => format!(" {:+} st", semitones),
+            }
+        )
+    }
+
+    // Selects the zone's preset on its channel
+    pub fn program_change(&self) -> [Message; 2] {
+        [
+            (0, [0xb0 | self.channel, BANK_SELECT, self.bank]),
+            (0, [0xc0 | self.channel, self.program, 0]),
+        ]
+    }
+}
+
+// Plays each key through every zone it falls in
+pub struct ZoneStage {
+    zones: Vec<Zone>,
+    channels: Vec<u8>, // each zone channel once, for controllers
+    // The (channel, note) each held key sounds, by its own channel and key
+    sounding: HashMap<(u8, u8), Vec<(u8, u8)>>,
+}
+
+impl ZoneStage {
+    pub fn new(zones: Vec<Zone>) -> Self {
+        let mut channels: Vec<u8> = zones.iter().map(|zone| zone.channel & 0x0f).collect();
+        channels.sort();
+        channels.dedup();
+        ZoneStage {
+            zones,
+            channels,
+            sounding: HashMap::new(),
+        }
+    }
+}
+
+impl Stage for ZoneStage {
+    fn process(&mut self, (time, [status, data1, data2]): Message, out: &mut Vec<Message>) {
+        let held = (status & 0x0f, data1);
+        let command = status & 0xf0;
+        match command {
+            0x90 if data2 > 0 => {
+                let notes: Vec<(u8, u8)> = self
+                    .zones
+                    .iter()
+                    .filter(|zone| zone.contains(data1))
+                    .filter_map(|zone| {
+                        let note = u8::try_from(data1 as i32 + zone.transpose as i32).ok()?;
+                        (note < 128).then_some((zone.channel & 0x0f, note))
+                    })
+                    .collect();
+                for (channel, note) in &notes {
+                    out.push((time, [command | channel, *note, data2]));
+                }
+                self.sounding.insert(held, notes);
+            }
+            0x80 | 0x90 | 0xa0 => {
+                let notes = match command {
+                    0xa0 => self.sounding.get(&held).cloned(),
+                    _ => self.sounding.remove(&held),
+                };
+                for (channel, note) in notes.unwrap_or_default() {
+                    out.push((time, [command | channel, note, data2]));
+                }
+            }
+            // controllers such as the pedal reach every zone
+            0xb0..=0xef => {
+                for channel in &self.channels {
+                    out.push((time, [command | channel, data1, data2]));
+                }
+            }
+            _ => out.push((time, [status, data1, data2])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theory::key::parse_note;
+
+    fn zone(low: &str, high: &str, channel: u8, transpose: i8) -> Zone {
+        Zone {
+            name: String::new(),
+            low: parse_note(low).unwrap(),
+            high: parse_note(high).unwrap(),
+            channel,
+            bank: 0,
+            program: 0,
+            transpose,
+        }
+    }
+
+    #[test]
+    fn test_split_and_layer() {
+        assert_eq!(parse_note("C4"), Some(60));
+        assert_eq!(parse_note("Bb2"), Some(46));
+        assert_eq!(parse_note("c#-1"), Some(1));
+        assert_eq!(parse_note("H3"), None);
+
+        // bass an octave down below C3, piano above, pad layered over the top half
+        let mut stage = ZoneStage::new(vec![
+            zone("0", "B2", 1, -12),
+            zone("C3", "127", 0, 0),
+            zone("C4", "127", 2, 0),
+        ]);
+        let mut out = Vec::new();
+        stage.process((0, [0x90, 40, 90]), &mut out);
+        stage.process((1, [0x90, 64, 90]), &mut out);
+        stage.process((2, [0xb0, 64, 127]), &mut out);
+        stage.process((3, [0x80, 64, 0]), &mut out);
+
+        assert_eq!(
+            out,
+            vec![
+                (0, [0x91, 28, 90]),
+                (1, [0x90, 64, 90]),
+                (1, [0x92, 64, 90]),
+                (2, [0xb0, 64, 127]),
+                (2, [0xb1, 64, 127]),
+                (2, [0xb2, 64, 127]),
+                (3, [0x80, 64, 0]),
+                (3, [0x82, 64, 0]),
+            ]
+        );
+    }
+}
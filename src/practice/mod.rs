@@ -0,0 +1,5 @@
+pub mod piece;
+pub mod play_along;
+pub mod progress;
+pub mod scoring;
+pub mod summary;
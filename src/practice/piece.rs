@@ -0,0 +1,156 @@
+use std::{error::Error, path::Path};
+
+use crate::rk_io::smf::read_smf;
+use crate::test::basic_tune;
+use crate::types::midi::{Message, MessageLog};
+
+// Notes below middle C go to the left hand unless configured otherwise
+pub const DEFAULT_SPLIT: u8 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+pub enum HandAssignment {
+    Split(u8),                   // notes below the split point are the left hand
+    Tracks { left: Vec<usize> }, // SMF tracks played by the left hand
+}
+
+impl HandAssignment {
+    fn hand(&self, track: usize, note: u8) -> Hand {
+        match self {
+            HandAssignment::Split(split) if note < *split => Hand::Left,
+            HandAssignment::Split(_) => Hand::Right,
+            HandAssignment::Tracks { left } if left.contains(&track) => Hand::Left,
+            HandAssignment::Tracks { .. } => Hand::Right,
+        }
+    }
+}
+
+// A note the player is expected to hit.
+// Times are micro seconds from the start of the piece.
+#[derive(Clone, Debug)]
+pub struct ExpectedNote {
+    pub time: u64,
+    pub duration: u64,
+    pub note: u8,
+    pub velocity: u8,
+    pub track: usize,
+    pub hand: Hand,
+}
+
+pub struct Piece {
+    pub name: String,
+    pub track_names: Vec<String>,
+    pub notes: Vec<ExpectedNote>, // sorted by time
+}
+
+fn is_note_on(status: u8, velocity: u8) -> bool {
+    (0x90..=0x9f).contains(&status) && velocity > 0
+}
+
+fn is_note_off(status: u8, velocity: u8) -> bool {
+    (0x80..=0x8f).contains(&status) || ((0x90..=0x9f).contains(&status) && velocity == 0)
+}
+
+impl Piece {
+    // Pair every note-on with the next note-off of the same key on the same track
+    // Hands start split at DEFAULT_SPLIT, see assign_hands
+    pub fn from_messages(
+        name: &str,
+        track_names: Vec<String>,
+        messages: &[(usize, Message)],
+    ) -> Self {
+        let start = messages
+            .iter()
+            .find(|(_, (_, [s, _, v]))| is_note_on(*s, *v))
+            .map(|(_, (t, _))| *t)
+            .unwrap_or(0);
+        let mut notes: Vec<ExpectedNote> = Vec::new();
+
+        for (i, (track, (t, [status, note, velocity]))) in messages.iter().enumerate() {
+            if !is_note_on(*status, *velocity) {
+                continue;
+            }
+
+            let end = messages[i + 1..]
+                .iter()
+                .find(|(tr, (_, [s, n, v]))| tr == track && n == note && is_note_off(*s, *v))
+                .map(|(_, (end, _))| *end)
+                .unwrap_or(*t);
+
+            notes.push(ExpectedNote {
+                time: t - start,
+                duration: end - t,
+                note: *note,
+                velocity: *velocity,
+                track: *track,
+                hand: HandAssignment::Split(DEFAULT_SPLIT).hand(*track, *note),
+            });
+        }
+
+        notes.sort_by_key(|n| n.time);
+
+        Piece {
+            name: name.to_string(),
+            track_names,
+            notes,
+        }
+    }
+
+    pub fn from_log<const L: usize>(name: &str, log: &MessageLog<L>) -> Self {
+        let messages: Vec<(usize, Message)> = log.data.iter().map(|msg| (0, *msg)).collect();
+        Self::from_messages(name, vec![name.to_string()], &messages)
+    }
+
+    pub fn from_smf(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let smf = read_smf(path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or("Untitled".to_string());
+        Ok(Self::from_messages(&name, smf.track_names, &smf.messages))
+    }
+
+    pub fn assign_hands(&mut self, hands: &HandAssignment) {
+        for note in self.notes.iter_mut() {
+            note.hand = hands.hand(note.track, note.note);
+        }
+    }
+
+    // Number of notes on each track, indexed like track_names
+    pub fn track_note_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.track_names.len()];
+        for note in &self.notes {
+            if let Some(count) = counts.get_mut(note.track) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    // Hand that most often plays this pitch, used to colour live input
+    pub fn hand_for(&self, note: u8) -> Hand {
+        let (left, right) =
+            self.notes
+                .iter()
+                .filter(|n| n.note == note)
+                .fold((0, 0), |(l, r), n| match n.hand {
+                    Hand::Left => (l + 1, r),
+                    Hand::Right => (l, r + 1),
+                });
+
+        match (left, right) {
+            (0, 0) if note < DEFAULT_SPLIT => Hand::Left,
+            (0, 0) => Hand::Right,
+            (l, r) if l > r => Hand::Left,
+            _ => Hand::Right,
+        }
+    }
+}
+
+pub fn builtin_pieces() -> Vec<Piece> {
+    vec![Piece::from_log("Basic Tune", &basic_tune::LOG)]
+}
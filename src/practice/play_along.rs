@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::practice::{
+    piece::{ExpectedNote, Hand, Piece},
+    scoring::{Judgement, Scorer},
+    summary::RunSummary,
+};
+use crate::types::midi::Message;
+
+// Time given to the player before the first expected note
+const LEAD_IN: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PracticeHands {
+    Both,
+    Only(Hand),
+}
+
+// What happens to the hand that is not being practised
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OtherHand {
+    Mute,
+    AutoPlay,
+}
+
+// A note of the piece in play-along time, tempo scaled, for drawing ahead of time
+pub struct UpcomingNote {
+    pub time: i64,
+    pub end: i64,
+    pub note: u8,
+    pub hand: Hand,
+    pub practised: bool, // false for the auto-played hand
+}
+
+// Keeps time for a piece and grades what is played against it
+pub struct PlayAlong {
+    pub piece: Piece,
+    pub tempo_percent: u32,
+    pub hands: PracticeHands,
+    pub other_hand: OtherHand,
+    pub scorer: Scorer,
+    auto_notes: Vec<ExpectedNote>, // other hand, already tempo scaled
+    auto_cursor: usize,
+    started: Instant,
+    started_at: u64, // Unix timestamp
+}
+
+impl PlayAlong {
+    // The clock runs from creation. midir timestamps count from when the port was
+    // opened, so call `start` straight after opening the connection.
+    pub fn new(
+        piece: Piece,
+        tempo_percent: u32,
+        hands: PracticeHands,
+        other_hand: OtherHand,
+    ) -> Self {
+        let (practised, other): (Vec<ExpectedNote>, Vec<ExpectedNote>) =
+            piece.notes.iter().cloned().partition(|n| match hands {
+                PracticeHands::Both => true,
+                PracticeHands::Only(hand) => n.hand == hand,
+            });
+
+        let scale = |micros: u64| micros * 100 / tempo_percent.max(1) as u64;
+        let auto_notes = match other_hand {
+            OtherHand::AutoPlay => other
+                .into_iter()
+                .map(|n| ExpectedNote {
+                    time: scale(n.time),
+                    duration: scale(n.duration),
+                    ..n
+                })
+                .collect(),
+            OtherHand::Mute => Vec::new(),
+        };
+
+        PlayAlong {
+            scorer: Scorer::new(&practised, tempo_percent),
+            piece,
+            tempo_percent,
+            hands,
+            other_hand,
+            auto_notes,
+            auto_cursor: 0,
+            started: Instant::now(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    // Restart the clock, with the lead-in still to come
+    pub fn start(&mut self) {
+        self.started = Instant::now();
+        self.auto_cursor = 0;
+    }
+
+    // Convert a micro second timestamp since start into piece time
+    fn piece_time(&self, since_start: u64) -> i64 {
+        since_start as i64 - LEAD_IN.as_micros() as i64
+    }
+
+    // Piece time now, negative during the lead-in
+    pub fn now(&self) -> i64 {
+        self.piece_time(self.started.elapsed().as_micros() as u64)
+    }
+
+    fn is_practised(&self, hand: Hand) -> bool {
+        match self.hands {
+            PracticeHands::Both => true,
+            PracticeHands::Only(practised) => practised == hand,
+        }
+    }
+
+    // Notes still to finish that start within `ahead` micro seconds. A muted hand
+    // is left out.
+    pub fn upcoming(&self, ahead: u64) -> Vec<UpcomingNote> {
+        let now = self.now();
+        let scale = |micros: u64| (micros * 100 / self.tempo_percent.max(1) as u64) as i64;
+        self.piece
+            .notes
+            .iter()
+            .map(|n| UpcomingNote {
+                time: scale(n.time),
+                end: scale(n.time + n.duration),
+                note: n.note,
+                hand: n.hand,
+                practised: self.is_practised(n.hand),
+            })
+            .take_while(|n| n.time <= now + ahead as i64)
+            .filter(|n| n.end > now && (n.practised || self.other_hand == OtherHand::AutoPlay))
+            .collect()
+    }
+
+    // Keys the practised hands should be holding down now
+    pub fn expected_keys(&self) -> Vec<u8> {
+        let now = self.now();
+        self.upcoming(0)
+            .into_iter()
+            .filter(|n| n.practised && n.time <= now)
+            .map(|n| n.note)
+            .collect()
+    }
+
+    pub fn note_on(&mut self, timestamp: u64, note: u8) -> Judgement {
+        let time = self.piece_time(timestamp);
+        self.scorer.note_on(time, note)
+    }
+
+    pub fn tick(&mut self) {
+        let time = self.now();
+        self.scorer.tick(time);
+    }
+
+    // Auto-played notes that have started since the last call
+    pub fn due_auto_notes(&mut self) -> &[ExpectedNote] {
+        let time = self.now();
+        let from = self.auto_cursor;
+        while self.auto_cursor < self.auto_notes.len()
+            && (self.auto_notes[self.auto_cursor].time as i64) <= time
+        {
+            self.auto_cursor += 1;
+        }
+        &self.auto_notes[from..self.auto_cursor]
+    }
+
+    // The auto-played hand as messages timed from the start of the clock, for the synth
+    pub fn auto_play_messages(&self) -> Vec<Message> {
+        let lead_in = LEAD_IN.as_micros() as u64;
+        self.auto_notes
+            .iter()
+            .flat_map(|n| {
+                [
+                    (lead_in + n.time, [0x90, n.note, n.velocity]),
+                    (lead_in + n.time + n.duration, [0x80, n.note, 0]),
+                ]
+            })
+            .collect()
+    }
+
+    // Seconds until the first note, None once the piece has started
+    pub fn count_in(&self) -> Option<u64> {
+        LEAD_IN
+            .checked_sub(self.started.elapsed())
+            .map(|remaining| remaining.as_secs() + 1)
+    }
+
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            piece: self.piece.name.clone(),
+            started_at: self.started_at,
+            tempo_percent: self.tempo_percent,
+            hands: match self.hands {
+                PracticeHands::Both => "both".to_string(),
+                PracticeHands::Only(Hand::Left) => "left".to_string(),
+                PracticeHands::Only(Hand::Right) => "right".to_string(),
+            },
+            expected_notes: self.scorer.expected_count() as u32,
+            counts: self.scorer.counts,
+            accuracy: self.scorer.accuracy(),
+            best_streak: self.scorer.best_streak,
+            completed: self.scorer.is_finished(),
+        }
+    }
+}
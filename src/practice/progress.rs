@@ -0,0 +1,172 @@
+use std::{
+    env,
+    fs::{self, create_dir_all},
+    io,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::practice::summary::RunSummary;
+use crate::types::recording::Recording;
+
+const DAY: u64 = 86_400;
+const WEEK: u64 = 7 * DAY;
+// 1970-01-01 was a Thursday, weeks start on Monday
+const WEEK_OFFSET: u64 = 3 * DAY;
+
+// One practice session, free playing or a play-along run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionLog {
+    pub started_at: u64, // Unix timestamp
+    pub duration: u64,   // seconds
+    pub note_count: u32,
+    pub piece: Option<String>,
+    pub accuracy: Option<f32>,
+    pub tempo_percent: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WeekTotal {
+    pub week_start: u64, // Unix timestamp of Monday 00:00 UTC
+    pub sessions: u32,
+    pub duration: u64, // seconds
+    pub note_count: u32,
+    pub pieces: Vec<String>,
+    pub best_accuracy: Option<f32>,
+    pub top_tempo: Option<u32>,
+}
+
+fn progress_path() -> PathBuf {
+    PathBuf::from(env::var("PROGRESS_FILE").unwrap_or("progress.ron".to_string()))
+}
+
+impl SessionLog {
+    pub fn from_session(recording: &Recording, run: Option<&RunSummary>) -> Self {
+        SessionLog {
+            started_at: recording.recorded_at,
+            duration: recording.duration() / 1_000_000,
+            note_count: recording
+                .messages
+                .iter()
+                .filter(|(_, [status, _, velocity])| {
+                    (0x90..=0x9f).contains(status) && *velocity > 0
+                })
+                .count() as u32,
+            piece: run.map(|r| r.piece.clone()),
+            accuracy: run.map(|r| r.accuracy),
+            tempo_percent: run.map(|r| r.tempo_percent),
+        }
+    }
+}
+
+// All logged sessions, oldest first
+pub fn load_sessions() -> Vec<SessionLog> {
+    let mut sessions: Vec<SessionLog> = fs::read_to_string(progress_path())
+        .ok()
+        .and_then(|text| ron::from_str(&text).ok())
+        .unwrap_or_default();
+    sessions.sort_by_key(|s| s.started_at);
+    sessions
+}
+
+pub fn log_session(session: SessionLog) -> io::Result<()> {
+    let mut sessions = load_sessions();
+    sessions.push(session);
+
+    let path = progress_path();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        create_dir_all(parent)?;
+    }
+    let text = ron::ser::to_string_pretty(&sessions, ron::ser::PrettyConfig::default())
+        .map_err(io::Error::other)?;
+    fs::write(path, text)
+}
+
+pub fn week_start(timestamp: u64) -> u64 {
+    ((timestamp + WEEK_OFFSET) / WEEK * WEEK).saturating_sub(WEEK_OFFSET)
+}
+
+// Totals per week, oldest first, including weeks without practice
+pub fn weekly_totals(sessions: &[SessionLog]) -> Vec<WeekTotal> {
+    let (Some(first), Some(last)) = (sessions.first(), sessions.last()) else {
+        return Vec::new();
+    };
+
+    let first_week = week_start(first.started_at);
+    let count = ((week_start(last.started_at) - first_week) / WEEK + 1) as usize;
+    let mut weeks: Vec<WeekTotal> = (0..count)
+        .map(|i| WeekTotal {
+            week_start: first_week + i as u64 * WEEK,
+            ..Default::default()
+        })
+        .collect();
+
+    for session in sessions {
+        let week = &mut weeks[((week_start(session.started_at) - first_week) / WEEK) as usize];
+        week.sessions += 1;
+        week.duration += session.duration;
+        week.note_count += session.note_count;
+        if let Some(piece) = &session.piece
+            && !week.pieces.contains(piece)
+        {
+            week.pieces.push(piece.clone());
+        }
+        if let Some(accuracy) = session.accuracy {
+            week.best_accuracy = Some(week.best_accuracy.map_or(accuracy, |a| a.max(accuracy)));
+        }
+        if let Some(tempo) = session.tempo_percent {
+            week.top_tempo = Some(week.top_tempo.map_or(tempo, |t| t.max(tempo)));
+        }
+    }
+
+    weeks
+}
+
+// Practice seconds for each of the last `days` days, oldest first
+pub fn daily_totals(sessions: &[SessionLog], today: u64, days: usize) -> Vec<u64> {
+    let today = today / DAY;
+    let mut totals = vec![0; days];
+    for session in sessions {
+        let day = session.started_at / DAY;
+        if day <= today && today - day < days as u64 {
+            totals[days - 1 - (today - day) as usize] += session.duration;
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(started_at: u64, duration: u64, accuracy: Option<f32>) -> SessionLog {
+        SessionLog {
+            started_at,
+            duration,
+            note_count: 10,
+            piece: accuracy.map(|_| "Basic Tune".to_string()),
+            accuracy,
+            tempo_percent: accuracy.map(|_| 80),
+        }
+    }
+
+    #[test]
+    fn test_weekly_totals() {
+        // Monday 2024-01-01 and the following Sunday, then two weeks later
+        let monday = 1_704_067_200;
+        let weeks = weekly_totals(&[
+            session(monday + 3_600, 600, None),
+            session(monday + 6 * DAY + 3_600, 300, Some(82.5)),
+            session(monday + 14 * DAY, 900, Some(90.0)),
+        ]);
+
+        assert_eq!(weeks.len(), 3);
+        assert_eq!(weeks[0].week_start, monday);
+        assert_eq!(weeks[0].sessions, 2);
+        assert_eq!(weeks[0].duration, 900);
+        assert_eq!(weeks[0].best_accuracy, Some(82.5));
+        assert_eq!(weeks[1].sessions, 0);
+        assert_eq!(weeks[2].pieces, vec!["Basic Tune".to_string()]);
+    }
+}
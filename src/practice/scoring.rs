@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+
+use crate::practice::piece::ExpectedNote;
+
+// Timing windows in micro seconds either side of the expected time
+const PERFECT_WINDOW: i64 = 50_000;
+const GOOD_WINDOW: i64 = 100_000;
+// Outside this window a played note is not matched to an expected one
+const HIT_WINDOW: i64 = 250_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Judgement {
+    Perfect,
+    Good,
+    Early,
+    Late,
+    Missed,
+    WrongNote,
+}
+
+impl Judgement {
+    pub fn is_hit(&self) -> bool {
+        !matches!(self, Judgement::Missed | Judgement::WrongNote)
+    }
+
+    // Contribution of a judgement towards the accuracy percentage
+    fn weight(&self) -> f32 {
+        match self {
+            Judgement::Perfect => 1.0,
+            Judgement::Good => 0.75,
+            Judgement::Early | Judgement::Late => 0.5,
+            Judgement::Missed | Judgement::WrongNote => 0.0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Judgement::Perfect => "Perfect",
+            Judgement::Good => "Good",
+            Judgement::Early => "Early",
+            Judgement::Late => "Late",
+            Judgement::Missed => "Missed",
+            Judgement::WrongNote => "Wrong note",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct JudgementCounts {
+    pub perfect: u32,
+    pub good: u32,
+    pub early: u32,
+    pub late: u32,
+    pub missed: u32,
+    pub wrong_notes: u32,
+}
+
+impl JudgementCounts {
+    fn add(&mut self, judgement: Judgement) {
+        let count = match judgement {
+            Judgement::Perfect => &mut self.perfect,
+            Judgement::Good => &mut self.good,
+            Judgement::Early => &mut self.early,
+            Judgement::Late => &mut self.late,
+            Judgement::Missed => &mut self.missed,
+            Judgement::WrongNote => &mut self.wrong_notes,
+        };
+        *count += 1;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.perfect + self.good + self.early + self.late + self.missed + self.wrong_notes
+    }
+}
+
+// Grades played notes against the expected notes of a piece.
+// All times are micro seconds relative to the start of the piece.
+pub struct Scorer {
+    expected: Vec<(i64, u8)>, // (time, note)
+    judged: Vec<bool>,
+    pub counts: JudgementCounts,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub last: Option<Judgement>,
+    weight_sum: f32,
+}
+
+impl Scorer {
+    // tempo_percent scales the piece, 50 plays it at half speed
+    pub fn new(notes: &[ExpectedNote], tempo_percent: u32) -> Self {
+        let tempo_percent = tempo_percent.max(1) as i64;
+        let expected: Vec<(i64, u8)> = notes
+            .iter()
+            .map(|n| (n.time as i64 * 100 / tempo_percent, n.note))
+            .collect();
+
+        Scorer {
+            judged: vec![false; expected.len()],
+            expected,
+            counts: JudgementCounts::default(),
+            streak: 0,
+            best_streak: 0,
+            last: None,
+            weight_sum: 0.0,
+        }
+    }
+
+    pub fn note_on(&mut self, time: i64, note: u8) -> Judgement {
+        let candidate = self
+            .expected
+            .iter()
+            .enumerate()
+            .filter(|(i, (t, n))| !self.judged[*i] && *n == note && (time - t).abs() <= HIT_WINDOW)
+            .min_by_key(|(_, (t, _))| (time - t).abs())
+            .map(|(i, (t, _))| (i, time - t));
+
+        let judgement = match candidate {
+            Some((index, delta)) => {
+                self.judged[index] = true;
+                match delta {
+                    d if d.abs() <= PERFECT_WINDOW => Judgement::Perfect,
+                    d if d.abs() <= GOOD_WINDOW => Judgement::Good,
+                    d if d < 0 => Judgement::Early,
+                    _ => Judgement::Late,
+                }
+            }
+            None => Judgement::WrongNote,
+        };
+
+        self.record(judgement);
+        judgement
+    }
+
+    // Mark every expected note whose hit window has passed as missed
+    pub fn tick(&mut self, now: i64) {
+        for index in 0..self.expected.len() {
+            if !self.judged[index] && self.expected[index].0 + HIT_WINDOW < now {
+                self.judged[index] = true;
+                self.record(Judgement::Missed);
+            }
+        }
+    }
+
+    fn record(&mut self, judgement: Judgement) {
+        self.counts.add(judgement);
+        self.weight_sum += judgement.weight();
+        self.last = Some(judgement);
+
+        if judgement.is_hit() {
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+
+    // Percentage 0.0-100.0 over everything judged so far
+    pub fn accuracy(&self) -> f32 {
+        match self.counts.total() {
+            0 => 100.0,
+            total => self.weight_sum / total as f32 * 100.0,
+        }
+    }
+
+    pub fn judged_count(&self) -> usize {
+        self.judged.iter().filter(|j| **j).count()
+    }
+
+    pub fn expected_count(&self) -> usize {
+        self.expected.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.judged.iter().all(|j| *j)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::practice::piece::Hand;
+
+    fn notes(times: &[(u64, u8)]) -> Vec<ExpectedNote> {
+        times
+            .iter()
+            .map(|(time, note)| ExpectedNote {
+                time: *time,
+                duration: 100_000,
+                note: *note,
+                velocity: 64,
+                track: 0,
+                hand: Hand::Right,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grades_by_timestamp_delta() {
+        let mut scorer = Scorer::new(
+            &notes(&[(0, 60), (500_000, 62), (1_000_000, 64), (1_500_000, 65)]),
+            100,
+        );
+
+        assert_eq!(scorer.note_on(20_000, 60), Judgement::Perfect);
+        assert_eq!(scorer.note_on(580_000, 62), Judgement::Good);
+        assert_eq!(scorer.note_on(800_000, 64), Judgement::Early);
+        assert_eq!(scorer.note_on(1_700_000, 65), Judgement::Late);
+        assert_eq!(scorer.note_on(1_700_000, 70), Judgement::WrongNote);
+
+        assert_eq!(scorer.streak, 0);
+        assert_eq!(scorer.best_streak, 4);
+        assert!(scorer.is_finished());
+    }
+
+    #[test]
+    fn test_tick_marks_missed_notes() {
+        let mut scorer = Scorer::new(&notes(&[(0, 60), (1_000_000, 62)]), 50);
+
+        scorer.tick(HIT_WINDOW + 1);
+        assert_eq!(scorer.counts.missed, 1);
+        assert!(!scorer.is_finished());
+
+        // at half tempo the second note is expected at 2s
+        assert_eq!(scorer.note_on(2_000_000, 62), Judgement::Perfect);
+        assert!(scorer.is_finished());
+        assert_eq!(scorer.accuracy(), 50.0);
+    }
+}
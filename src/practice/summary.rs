@@ -0,0 +1,70 @@
+use std::{
+    env,
+    fs::{self, create_dir_all},
+    io,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::practice::scoring::JudgementCounts;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub piece: String,
+    pub started_at: u64, // Unix timestamp
+    pub tempo_percent: u32,
+    #[serde(default)]
+    pub hands: String, // both, left or right
+    pub expected_notes: u32,
+    pub counts: JudgementCounts,
+    pub accuracy: f32,
+    pub best_streak: u32,
+    pub completed: bool, // false if the run was quit early
+}
+
+fn summary_dir() -> PathBuf {
+    PathBuf::from(env::var("SUMMARY_DIR").unwrap_or("summaries".to_string()))
+}
+
+// One file per run so runs can be compared across days
+pub fn write_summary(summary: &RunSummary) -> io::Result<PathBuf> {
+    let dir = summary_dir();
+    create_dir_all(&dir)?;
+
+    let slug: String = summary
+        .piece
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let path = dir.join(format!("{}-{}.ron", summary.started_at, slug));
+
+    let text = ron::ser::to_string_pretty(summary, ron::ser::PrettyConfig::default())
+        .map_err(io::Error::other)?;
+    fs::write(&path, text)?;
+
+    Ok(path)
+}
+
+// Earlier runs of a piece, oldest first
+pub fn previous_runs(piece: &str) -> Vec<RunSummary> {
+    let Ok(entries) = fs::read_dir(summary_dir()) else {
+        return Vec::new();
+    };
+
+    let mut runs: Vec<RunSummary> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|text| ron::from_str::<RunSummary>(&text).ok())
+        .filter(|run| run.piece == piece)
+        .collect();
+
+    runs.sort_by_key(|run| run.started_at);
+    runs
+}
@@ -0,0 +1,83 @@
+// ---
+use crate::pipeline::arp::{ArpPattern, ArpSettings, ArpSync};
+use crate::rk_io::midi_out::read_midi_out;
+use crate::rk_io::user_input::read_line;
+use crate::util::prefs::Prefs;
+
+fn read_pattern(current: ArpPattern) -> ArpPattern {
+    for (i, pattern) in ArpPattern::ALL.iter().enumerate() {
+        println!("  ({}) {}", i, pattern.label());
+    }
+    read_line(&format!("Pattern [{}]: ", current.label()))
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| ArpPattern::ALL.get(i).copied())
+        .unwrap_or(current)
+}
+
+fn read_sync(current: ArpSync) -> ArpSync {
+    let shown = match current {
+        ArpSync::Tempo(bpm) => bpm.to_string(),
+        ArpSync::MidiClock => "c".to_string(),
+    };
+    let input = read_line(&format!(
+        "Tempo in bpm, or c to follow MIDI clock [{}]: ",
+        shown
+    ));
+    match input.as_str() {
+        "c" | "clock" => ArpSync::MidiClock,
+        _ => match input.parse::<u16>() {
+            Ok(bpm) => ArpSync::Tempo(bpm.clamp(20, 300)),
+            Err(_) => current,
+        },
+    }
+}
+
+// Set up the arpeggiator for live playing from the next session on
+pub fn select_arp() {
+    let mut prefs = Prefs::load();
+    let arp = prefs.arp.clone();
+    println!(
+        "Arpeggiator is {}: {}",
+        if arp.enabled { "on" } else { "off" },
+        arp.label()
+    );
+
+    let enabled = match read_line("Arpeggiate live playing? [y/n, keep]: ").as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => arp.enabled,
+    };
+    if !enabled {
+        prefs.arp.enabled = false;
+    } else {
+        let octaves = read_line(&format!("Octaves, 1-4 [{}]: ", arp.octaves))
+            .parse::<u8>()
+            .map_or(arp.octaves, |o| o.clamp(1, 4));
+        let steps_per_beat = read_line(&format!(
+            "Steps per beat, 1 quarters, 2 eighths, 3 triplets, 4 sixteenths [{}]: ",
+            arp.steps_per_beat
+        ))
+        .parse::<u8>()
+        .map_or(arp.steps_per_beat, |s| s.clamp(1, 4));
+        let gate = read_line(&format!("Gate in percent [{:.0}]: ", arp.gate * 100.0))
+            .parse::<f32>()
+            .map_or(arp.gate, |g| (g / 100.0).clamp(0.1, 1.0));
+
+        prefs.arp = ArpSettings {
+            enabled,
+            pattern: read_pattern(arp.pattern),
+            octaves,
+            steps_per_beat,
+            gate,
+            sync: read_sync(arp.sync),
+            midi_out: read_midi_out("Also send the arpeggio to", arp.midi_out.clone()),
+        };
+    }
+
+    match prefs.save() {
+        Ok(()) if prefs.arp.enabled => println!("Arpeggiator on: {}", prefs.arp.label()),
+        Ok(()) => println!("Arpeggiator off"),
+        Err(e) => eprintln!("Failed to save preference: {}", e),
+    }
+}
@@ -1,14 +1,41 @@
 use std::{
-    fs::{DirEntry, read_dir},
+    env::{self, temp_dir},
     path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
     thread::{self, JoinHandle},
 };
 
-use cpal::{
-    StreamConfig,
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-};
 use midi_player::{Player, PlayerController, Settings};
+// ---
+use crate::audio::device::{OutputConfig, list_output_devices, open_device};
+use crate::audio::effects::{EffectsHandle, WithEffects};
+use crate::audio::live_synth::{LiveSynth, LiveSynthHandle};
+use crate::audio::oscillator::{OscillatorSynth, Waveform};
+use crate::audio::sink::{AudioSink, open_sink};
+use crate::audio::source::AudioSource;
+use crate::audio::soundfont::{
+    default_soundfont, find_soundfonts, remember_soundfont, search_paths,
+};
+use crate::rk_io::smf::write_smf;
+use crate::rk_io::user_input::{get_input, read_line};
+use crate::rk_ui::soundfont_view::run_soundfont_select;
+use crate::theory::tuning::Tuning;
+use crate::types::midi::Message;
+use crate::util::prefs::{Instrument, Prefs};
+
+// Voices the live synth plays at once, unless POLYPHONY is set
+const DEFAULT_POLYPHONY: usize = 32;
+
+// The handles the UI uses to control a running live synth
+pub struct LiveAudio {
+    pub synth: LiveSynthHandle,
+    pub output: OutputConfig,
+    pub effects: EffectsHandle,
+    pub tuning: Tuning,
+}
 
 /* use alike
 fn main() {
@@ -38,87 +65,262 @@ fn main() {
 }
 */
 
-pub fn start_audio_loop(mut player: Player) {
-    let host = cpal::default_host();
-    let device = host
-        .default_output_device()
-        .expect("No output device available");
-    let channels = 2 as usize;
-    let config = StreamConfig {
-        channels: channels as u16,
-        sample_rate: cpal::SampleRate(player.settings().sample_rate),
-        buffer_size: cpal::BufferSize::Fixed(player.settings().audio_buffer_size),
-    };
+// A player's audio thread, which stops when told to
+pub struct AudioLoop {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+}
 
-    let err_fn = |err| eprintln!("An error occurred on the output audio stream: {}", err);
-
-    let mut left = vec![0f32; player.settings().audio_buffer_size as usize];
-    let mut right = vec![0f32; player.settings().audio_buffer_size as usize];
-
-    let stream = device
-        .build_output_stream(
-            &config.into(),
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let sample_count = data.len() / channels;
-
-                player.render(&mut left, &mut right);
-
-                if !left.is_empty() {
-                    for i in 0..sample_count {
-                        data[channels * i] = left[i];
-                        data[channels * i + 1] = right[i];
-                    }
-                }
-            },
-            err_fn,
-            None,
-        )
-        .unwrap();
+impl AudioLoop {
+    // Close the output and wait for the thread to end
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        self.handle.join().ok();
+    }
+}
 
-    stream.play().expect("cannot run audio stream");
+// Play a source until `stop` is set. It must render at the sink's config.
+pub fn start_audio_loop(
+    sink: Box<dyn AudioSink>,
+    source: impl AudioSource + 'static,
+    stop: &AtomicBool,
+) {
+    if let Err(e) = sink.run(Box::new(source), stop) {
+        eprintln!("Audio output stopped: {}", e);
+    }
+}
 
-    std::thread::park();
+// The sink chosen by AUDIO_SINK, with the device settings from the preferences
+fn open_output(stream: &str) -> Box<dyn AudioSink> {
+    open_sink(stream, &Prefs::load().output)
 }
 
-pub fn create_player(soundfont: &str) -> (Player, PlayerController) {
-    let settings = Settings::builder().build();
-    let (player, controller) = Player::new(soundfont, settings).unwrap();
-    return (player, controller);
+pub fn create_player(
+    soundfont: &Path,
+    output: &OutputConfig,
+) -> Result<(Player, PlayerController), String> {
+    let settings = Settings::builder()
+        .sample_rate(output.sample_rate)
+        .audio_buffer_size(output.buffer_size())
+        .build();
+    Player::new(&soundfont.to_string_lossy(), settings)
+        .map_err(|e| format!("Failed to load {}: {}", soundfont.display(), e))
 }
 
-fn list_soundfonts() -> Result<Vec<DirEntry>, String> {
-    let path = Path::new("src/sf2");
-    let entries = read_dir(path)
-        .unwrap()
-        .filter_map(|e| e.ok())
-        .collect::<Vec<DirEntry>>();
+// Choose the default soundfont from those in the search paths
+pub fn select_soundfont() {
+    let files = find_soundfonts();
+    if files.is_empty() {
+        println!(
+            "No .sf2 files found in {}. Set SOUNDFONT_PATH to search elsewhere.",
+            search_paths()
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        return;
+    }
+
+    match run_soundfont_select(files, default_soundfont().ok()) {
+        Ok(Some(path)) => match remember_soundfont(&path) {
+            Ok(()) => println!("Default soundfont set to {}", path.display()),
+            Err(e) => eprintln!("Failed to save preference: {}", e),
+        },
+        Ok(None) => (),
+        Err(e) => eprintln!("UI error: {}", e),
+    }
+}
 
-    for entry in &entries {
-        let path = entry.path();
+pub fn spawn_audio_loop() -> Result<(AudioLoop, PlayerController), String> {
+    let soundfont = default_soundfont()?;
+    let sink = open_output("playback");
+    let (player, controller) = create_player(&soundfont, sink.config())?;
+    let effects = EffectsHandle::new(Prefs::load().effects);
+    let source = WithEffects::new(player, effects, sink.config().sample_rate);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let handle = thread::spawn(move || {
+        start_audio_loop(sink, source, &thread_stop);
+    });
+    Ok((AudioLoop { handle, stop }, controller))
+}
+
+fn polyphony() -> usize {
+    env::var("POLYPHONY")
+        .ok()
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_POLYPHONY)
+        .clamp(8, 256)
+}
+
+// The chosen tuning, or 12-TET when its files can no longer be read
+pub fn load_tuning() -> Tuning {
+    Prefs::load().tuning.load().unwrap_or_else(|e| {
+        println!("{}, using 12-TET.", e);
+        Tuning::equal()
+    })
+}
+
+// The chosen instrument ready to play at the given rate and tuning.
+// Falls back to the built-in electric piano when no soundfont is available.
+pub fn create_live_source(
+    sample_rate: u32,
+    tuning: &Tuning,
+) -> Result<(Box<dyn AudioSource>, LiveSynthHandle), String> {
+    let waveform = match Prefs::load().instrument {
+        Instrument::Builtin(waveform) => waveform,
+        Instrument::Soundfont => match default_soundfont() {
+            Ok(soundfont) => {
+                let (mut synth, handle) = LiveSynth::new(&soundfont, sample_rate, polyphony())?;
+                synth.set_tuning(tuning.clone());
+                return Ok((Box::new(synth), handle));
+            }
+            Err(e) => {
+                println!("{}, using the built-in electric piano.", e);
+                Waveform::EPiano
+            }
+        },
+    };
 
-        if let Some(name) = path.file_name() {
-            println!("{}", name.to_string_lossy());
+    let (mut synth, handle) = OscillatorSynth::new(waveform, sample_rate, polyphony());
+    synth.set_tuning(tuning.clone());
+    Ok((Box::new(synth), handle))
+}
+
+// Sound for the keys as they are played, through the chosen instrument and effects
+pub fn spawn_live_synth() -> Result<LiveAudio, String> {
+    let sink = open_output("live");
+    let output = sink.config().clone();
+    let tuning = load_tuning();
+    let (source, synth) = create_live_source(output.sample_rate, &tuning)?;
+    let effects = EffectsHandle::new(Prefs::load().effects);
+    let source = WithEffects::new(source, effects.clone(), output.sample_rate);
+
+    // live sound plays for as long as the program runs
+    thread::spawn(move || {
+        start_audio_loop(sink, source, &AtomicBool::new(false));
+    });
+    Ok(LiveAudio {
+        synth,
+        output,
+        effects,
+        tuning,
+    })
+}
+
+// Choose the output device, sample rate and buffer size
+pub fn select_output() {
+    let mut prefs = Prefs::load();
+    let devices = list_output_devices();
+
+    println!("Output devices:");
+    for (i, device) in devices.iter().enumerate() {
+        let mut notes = Vec::new();
+        if device.is_default {
+            notes.push("system default");
         }
+        if prefs.output.device.as_ref() == Some(&device.name) {
+            notes.push("current");
+        }
+        let channels: Vec<String> = device.channels.iter().map(|c| c.to_string()).collect();
+        println!(
+            "  ({}) {} - {} channels, {}-{} Hz{}",
+            i,
+            device.name,
+            channels.join("/"),
+            device.min_rate,
+            device.max_rate,
+            if notes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", notes.join(", "))
+            }
+        );
     }
 
-    if entries.is_empty() {
-        return Err("No files found".to_string());
-    } else {
-        return Ok(entries);
+    let input = read_line("Device number or name, d for the system default [keep]: ");
+    match input.as_str() {
+        "" => (),
+        "d" | "default" => prefs.output.device = None,
+        _ => {
+            let found = input
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| devices.get(i))
+                .or_else(|| devices.iter().find(|d| d.name == input));
+            match found {
+                Some(device) => prefs.output.device = Some(device.name.clone()),
+                None => println!("No device \"{}\", keeping the current one.", input),
+            }
+        }
+    }
+
+    let input = read_line(&format!("Sample rate [{}]: ", prefs.output.sample_rate()));
+    if let Ok(rate) = input.parse::<u32>() {
+        prefs.output.sample_rate = Some(rate.clamp(8_000, 192_000));
+    }
+    let input = read_line(&format!(
+        "Buffer size in frames [{}]: ",
+        prefs.output.buffer_size()
+    ));
+    if let Ok(frames) = input.parse::<u32>() {
+        prefs.output.buffer_size = Some(frames.clamp(16, 8_192));
     }
-}
 
-fn select_soundfont() -> String {
-    let entries = list_soundfonts();
-		
-		return "".to_string()
+    if let Err(e) = prefs.save() {
+        eprintln!("Failed to save preference: {}", e);
+    }
+    match open_device(&prefs.output) {
+        Ok((_, config)) => println!("Output: {}", config),
+        Err(e) => println!("Output unavailable: {}", e),
+    }
 }
 
-pub fn spawn_audio_loop() -> JoinHandle<()> {
-    let soundfont = select_soundfont();
-    let (player, _controller) = create_player(&soundfont);
+// Choose between the soundfont and the built-in waveforms for live playing
+pub fn select_instrument() {
+    let mut prefs = Prefs::load();
+    let instruments: Vec<Instrument> = std::iter::once(Instrument::Soundfont)
+        .chain(Waveform::ALL.into_iter().map(Instrument::Builtin))
+        .collect();
 
-    return thread::spawn(|| {
-        start_audio_loop(player);
-    });
+    println!("Instruments:");
+    for (i, instrument) in instruments.iter().enumerate() {
+        let marker = if *instrument == prefs.instrument {
+            " (current)"
+        } else {
+            ""
+        };
+        println!("  ({}) {}{}", i, instrument.label(), marker);
+    }
+
+    let keys: Vec<String> = (0..instruments.len()).map(|i| i.to_string()).collect();
+    let options: Vec<(&str, Instrument)> = keys
+        .iter()
+        .map(String::as_str)
+        .zip(instruments.iter().copied())
+        .collect();
+
+    if let Some(instrument) = get_input("Select instrument: ", &options) {
+        prefs.instrument = instrument;
+        match prefs.save() {
+            Ok(()) => println!("Instrument set to {}", instrument.label()),
+            Err(e) => eprintln!("Failed to save preference: {}", e),
+        }
+    }
+}
+
+// Hand messages to a player through a temporary file, replacing what it had loaded
+pub fn load_messages(
+    controller: &mut PlayerController,
+    name: &str,
+    messages: &[Message],
+) -> Result<(), String> {
+    let path = temp_dir().join(format!("rust-keys-{}.mid", name));
+    write_smf(&path, messages).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    controller
+        .set_file(Some(path))
+        .map_err(|e| format!("Failed to load {}: {}", name, e))
 }
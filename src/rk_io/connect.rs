@@ -1,10 +1,29 @@
 use midir::{MidiInput, MidiInputConnection};
-use std::io::{Write, stdin, stdout};
-use std::sync::mpsc::Sender;
+use std::{
+    io::{Write, stdin, stdout},
+    sync::mpsc::Sender,
+};
 // ---
+use crate::pipeline::{
+    Stage,
+    arp::Arpeggiator,
+    harmony::{GeneratedNotes, HarmonyStage},
+    spawn_clocked,
+    transpose::TransposeHandle,
+    zones::ZoneStage,
+};
+use crate::practice::progress::{SessionLog, log_session};
+use crate::practice::summary::RunSummary;
+use crate::rk_io::audio_out::spawn_live_synth;
+use crate::rk_io::midi_out::open_midi_out;
+use crate::rk_io::router::Router;
+use crate::rk_io::user_input::read_line;
 use crate::rk_io::watcher::spawn_watcher;
+use crate::rk_ui::types::UiEngine;
 use crate::rk_ui::ui::run_app;
-use crate::types;
+use crate::types::midi::Message;
+use crate::types::recording::Recording;
+use crate::util::prefs::Prefs;
 
 fn print_ports(midi: &MidiInput) {
     let ports: Vec<midir::MidiInputPort> = midi.ports();
@@ -18,10 +37,10 @@ fn print_ports(midi: &MidiInput) {
     }
 }
 
-fn open_conn(
+pub fn open_conn(
     midi: MidiInput,
     port: &midir::MidiInputPort,
-    tx: Sender<types::midi::Message>,
+    mut router: Router,
 ) -> MidiInputConnection<()> {
     println!("Opening connection...");
     return midi
@@ -29,15 +48,12 @@ fn open_conn(
             &port,
             "midir-read-input",
             move |now: u64, message: &[u8], _| {
-                if let Ok(msg) = <[u8; 3]>::try_from(message) {
-                    tx.send((now, msg)).ok();
-                } else if let Ok(msg) = <[u8; 1]>::try_from(message) {
-                    tx.send((now, [msg[0], 0, 0])).ok();
-                } else {
-                    panic!(
-                        "Midi message out of bounds! ts: {} data: {:?}",
-                        now, message
-                    );
+                // shorter messages, like program change or time code, are padded.
+                // Longer ones can only be sysex, which is ignored.
+                if (1..=3).contains(&message.len()) {
+                    let mut msg = [0u8; 3];
+                    msg[..message.len()].copy_from_slice(message);
+                    router.send((now, msg));
                 }
             },
             (),
@@ -45,13 +61,12 @@ fn open_conn(
         .unwrap();
 }
 
-pub fn select_device(midi: MidiInput) -> Option<MidiInputConnection<()>> {
+// Prompt until a valid port index is entered
+pub fn prompt_port(midi: &MidiInput) -> usize {
     let ports = midi.ports();
     let mut input = String::new();
 
-    let (tx, rx) = spawn_watcher();
-
-    print_ports(&midi);
+    print_ports(midi);
     match ports.len() {
         0 => panic!("No ports available"),
         _ => loop {
@@ -61,14 +76,7 @@ pub fn select_device(midi: MidiInput) -> Option<MidiInputConnection<()>> {
             stdin().read_line(&mut input).unwrap();
 
             match input.trim().parse::<usize>() {
-                Ok(index) if index < ports.len() => {
-                    let conn = open_conn(midi, &ports[index], tx);
-                    if let Err(e) = run_app(rx) {
-                        eprintln!("UI error: {}", e);
-                        return None;
-                    }
-                    return Some(conn);
-                }
+                Ok(index) if index < ports.len() => return index,
                 Ok(index) => println!(
                     "Invalid selection: {}. Must be less than {}.",
                     index,
@@ -81,7 +89,149 @@ pub fn select_device(midi: MidiInput) -> Option<MidiInputConnection<()>> {
                 ),
             }
             println!("Try again.\n");
-            print_ports(&midi);
+            print_ports(midi);
         },
-    };
+    }
+}
+
+// The router for live playing, with the transpose from last time shared with the UI.
+// Harmony goes to every target, so the UI and MIDI thru get the added notes too.
+pub fn live_router(ui: Sender<Message>, engine: &mut UiEngine) -> Router {
+    let prefs = Prefs::load();
+    let transpose = TransposeHandle::new(prefs.transpose);
+    engine.transpose = Some(transpose.clone());
+    let mut router = Router::from_prefs(ui, transpose);
+
+    if prefs.harmony.enabled {
+        let generated = GeneratedNotes::new();
+        router.add_stage(Box::new(HarmonyStage::new(
+            &prefs.harmony,
+            generated.clone(),
+        )));
+        engine.generated = Some(generated);
+    }
+    if let Some(port) = &prefs.midi_thru {
+        match open_midi_out(port) {
+            Ok(thru) => router.add_target(thru),
+            Err(e) => eprintln!("{}, no MIDI thru.", e),
+        }
+    }
+    router
+}
+
+// Ask whether the keys should sound, and route notes to the synth if so
+pub fn prompt_live_synth(router: &mut Router, engine: &mut UiEngine) {
+    if let "n" | "no" = read_line("Play sound through the synth? [Y/n]: ").as_str() {
+        return;
+    }
+    match spawn_live_synth() {
+        Ok(live) => {
+            // zones only change what sounds, the UI and recording keep the keys played
+            let prefs = Prefs::load();
+            let zones = prefs.zones;
+            for message in zones.iter().flat_map(|zone| zone.program_change()) {
+                live.synth.events.send(message).ok();
+            }
+            if prefs.arp.enabled {
+                // the arpeggio plays on its own thread, through the zones after it
+                let mut stages: Vec<Box<dyn Stage>> =
+                    vec![Box::new(Arpeggiator::new(prefs.arp.clone()))];
+                if !zones.is_empty() {
+                    stages.push(Box::new(ZoneStage::new(zones.clone())));
+                }
+                let mut targets = vec![live.synth.events.clone()];
+                if let Some(port) = &prefs.arp.midi_out {
+                    match open_midi_out(port) {
+                        Ok(out) => targets.push(out),
+                        Err(e) => eprintln!("{}, arpeggio only through the synth.", e),
+                    }
+                }
+                router.add_clocked_target(spawn_clocked(stages, targets));
+                engine.arp = Some(prefs.arp.label());
+            } else if zones.is_empty() {
+                router.add_target(live.synth.events.clone());
+            } else {
+                router.add_target_through(
+                    live.synth.events.clone(),
+                    Box::new(ZoneStage::new(zones.clone())),
+                );
+            }
+            engine.zones = zones;
+            engine.synth = Some(live.synth);
+            engine.output = Some(live.output);
+            engine.effects = Some(live.effects);
+            engine.tuning = (!live.tuning.is_equal()).then_some(live.tuning.name);
+        }
+        Err(e) => eprintln!("Live sound unavailable: {}", e),
+    }
+}
+
+pub fn select_device(midi: MidiInput) -> Option<MidiInputConnection<()>> {
+    let ports = midi.ports();
+    let (tx, rx) = spawn_watcher();
+
+    let index = prompt_port(&midi);
+    let mut engine = UiEngine::new("Session");
+    let mut router = live_router(tx, &mut engine);
+    prompt_live_synth(&mut router, &mut engine);
+
+    let conn = open_conn(midi, &ports[index], router);
+    engine.start_clock();
+    match run_app(rx, engine) {
+        Ok(engine) => {
+            save_recording(&engine.recording);
+            log_progress(&engine.recording, None);
+            remember_settings(&engine);
+        }
+        Err(e) => {
+            eprintln!("UI error: {}", e);
+            return None;
+        }
+    }
+    Some(conn)
+}
+
+pub fn save_recording(recording: &Recording) {
+    if recording.is_empty() {
+        return;
+    }
+    match recording.save() {
+        Ok(path) => println!("Recording saved to {}", path.display()),
+        Err(e) => eprintln!("Failed to save recording: {}", e),
+    }
+}
+
+// Keep effect, transpose and scale highlight changes made in the UI for next time
+pub fn remember_settings(engine: &UiEngine) {
+    let mut prefs = Prefs::load();
+    let mut changed = false;
+    if let Some(effects) = &engine.effects
+        && prefs.effects != effects.get()
+    {
+        prefs.effects = effects.get();
+        changed = true;
+    }
+    if let Some(transpose) = &engine.transpose
+        && prefs.transpose != transpose.get()
+    {
+        prefs.transpose = transpose.get();
+        changed = true;
+    }
+    if prefs.scale_highlight != engine.scale_highlight {
+        prefs.scale_highlight = engine.scale_highlight;
+        changed = true;
+    }
+    if changed && let Err(e) = prefs.save() {
+        eprintln!("Failed to save settings: {}", e);
+    }
+}
+
+// Add the session to the long-term progress store
+pub fn log_progress(recording: &Recording, run: Option<&RunSummary>) {
+    if recording.is_empty() && run.is_none() {
+        return;
+    }
+    if let Err(e) = log_session(SessionLog::from_session(recording, run)) {
+        eprintln!("Failed to log practice session: {}", e);
+    }
 }
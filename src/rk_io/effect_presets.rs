@@ -0,0 +1,71 @@
+// ---
+use crate::audio::effects::{Effect, EffectSettings};
+use crate::rk_io::user_input::read_line;
+use crate::util::prefs::Prefs;
+
+fn describe(settings: &EffectSettings) -> String {
+    let enabled: Vec<&str> = [Effect::Eq, Effect::Chorus, Effect::Reverb, Effect::Limiter]
+        .into_iter()
+        .filter(|effect| settings.enabled(*effect))
+        .map(|effect| effect.label())
+        .collect();
+    if enabled.is_empty() {
+        "all off".to_string()
+    } else {
+        enabled.join(", ")
+    }
+}
+
+// A preset by its number in the list or its name
+fn find_preset(prefs: &Prefs, input: &str) -> Option<String> {
+    input
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| prefs.effect_presets.keys().nth(i))
+        .or_else(|| prefs.effect_presets.keys().find(|name| *name == input))
+        .cloned()
+}
+
+// Save the current effects under a name, or load or delete a saved preset.
+// Presets can also be stepped through live with [ and ] in the effects panel.
+pub fn select_effect_presets() {
+    let mut prefs = Prefs::load();
+    println!("Current effects: {}", describe(&prefs.effects));
+    println!("Presets:");
+    for (i, (name, settings)) in prefs.effect_presets.iter().enumerate() {
+        println!("  ({}) {} - {}", i, name, describe(settings));
+    }
+
+    let action = read_line("(s)ave current, (l)oad or (d)elete a preset: ");
+    match action.as_str() {
+        "s" | "save" => {
+            let name = read_line("Preset name: ");
+            if name.is_empty() {
+                return;
+            }
+            prefs
+                .effect_presets
+                .insert(name.clone(), prefs.effects.clone());
+            println!("Saved preset {}", name);
+        }
+        "l" | "load" | "d" | "delete" => {
+            let input = read_line("Preset number or name: ");
+            let Some(name) = find_preset(&prefs, &input) else {
+                println!("No preset \"{}\"", input);
+                return;
+            };
+            if action.starts_with('l') {
+                prefs.effects = prefs.effect_presets[&name].clone();
+                println!("Loaded preset {}", name);
+            } else {
+                prefs.effect_presets.remove(&name);
+                println!("Deleted preset {}", name);
+            }
+        }
+        _ => return,
+    }
+
+    if let Err(e) = prefs.save() {
+        eprintln!("Failed to save preference: {}", e);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fs::write;
+// ---
+use crate::notation::{
+    abc::to_abc, lilypond::to_lilypond, musicxml::to_musicxml, quantise::quantise,
+};
+use crate::rk_io::recordings::select_recording;
+use crate::rk_io::user_input::read_line;
+use crate::theory::key::{key_name, parse_key};
+use crate::types::recording::Recording;
+
+// Let the user correct tempo, metre and key before quantising
+fn confirm_metadata(recording: &mut Recording) {
+    let input = read_line(&format!("Tempo bpm [{}]: ", recording.bpm));
+    if let Ok(bpm) = input.parse::<f32>() {
+        recording.bpm = bpm.clamp(20.0, 300.0);
+    }
+
+    let (beats, beat_type) = recording.time_signature;
+    let input = read_line(&format!("Time signature [{}/{}]: ", beats, beat_type));
+    let parsed = input
+        .split_once('/')
+        .map(|(beats, beat_type)| (beats.trim().parse::<u8>(), beat_type.trim().parse::<u8>()));
+    match parsed {
+        Some((Ok(beats), Ok(beat_type))) if beats > 0 && [1, 2, 4, 8, 16].contains(&beat_type) => {
+            recording.time_signature = (beats, beat_type);
+        }
+        _ => (),
+    }
+
+    let current = recording.key.unwrap_or_default();
+    let input = read_line(&format!("Key, e.g. Bb or F#m [{}]: ", key_name(current)));
+    recording.key = Some(parse_key(&input).unwrap_or(current));
+}
+
+// Short phrases are also printed so they can be pasted elsewhere
+const PRINT_MAX_MEASURES: usize = 8;
+
+#[derive(Clone, Copy)]
+enum Format {
+    MusicXml,
+    LilyPond,
+    Abc,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::MusicXml => "musicxml",
+            Format::LilyPond => "ly",
+            Format::Abc => "abc",
+        }
+    }
+}
+
+fn select_format() -> Format {
+    match read_line("Format (m)usicXML, (l)ilyPond or (a)bc [m]: ").as_str() {
+        "l" | "ly" | "lilypond" => Format::LilyPond,
+        "a" | "abc" => Format::Abc,
+        _ => Format::MusicXml,
+    }
+}
+
+pub fn select_export() {
+    let Some((path, mut recording)) = select_recording() else {
+        return;
+    };
+    let format = select_format();
+    confirm_metadata(&mut recording);
+
+    let score = quantise(&recording, recording.key.unwrap_or_default());
+    let text = match format {
+        Format::MusicXml => to_musicxml(&score),
+        Format::LilyPond => to_lilypond(&score),
+        Format::Abc => to_abc(&score),
+    };
+
+    let out = path.with_extension(format.extension());
+    match write(&out, &text) {
+        Ok(()) => println!("Exported to {}", out.display()),
+        Err(e) => eprintln!("Export failed: {}", e),
+    }
+
+    if !matches!(format, Format::MusicXml) && score.measures.len() <= PRINT_MAX_MEASURES {
+        println!("\n{}", text);
+    }
+}
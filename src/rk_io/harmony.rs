@@ -0,0 +1,118 @@
+use midir::MidiInput;
+use std::{collections::BTreeSet, sync::mpsc::channel};
+// ---
+use crate::pipeline::harmony::{Harmony, HarmonySettings, Voicing};
+use crate::rk_io::connect::{open_conn, prompt_port};
+use crate::rk_io::router::Router;
+use crate::rk_io::user_input::read_line;
+use crate::theory::key::{mode_name, parse_key, parse_note, tonic_name};
+use crate::util::prefs::Prefs;
+
+// Semitones above the lowest note
+fn chord_shape(notes: &BTreeSet<u8>) -> Vec<u8> {
+    let lowest = notes.first().copied().unwrap_or(0);
+    notes.iter().map(|note| note - lowest).collect()
+}
+
+// Wait for a chord to be played and let go of
+fn play_chord(midi: MidiInput) -> BTreeSet<u8> {
+    let index = prompt_port(&midi);
+    let ports = midi.ports();
+    let (tx, rx) = channel();
+    let _conn = open_conn(midi, &ports[index], Router::new(tx));
+    println!("Play the chord, then let go.");
+
+    let mut chord = BTreeSet::new();
+    let mut held = BTreeSet::new();
+    for (_, [status, note, velocity]) in rx {
+        match status & 0xf0 {
+            0x90 if velocity > 0 => {
+                held.insert(note);
+                chord.insert(note);
+            }
+            0x80 | 0x90 => {
+                held.remove(&note);
+                if held.is_empty() {
+                    break;
+                }
+            }
+            _ => (),
+        }
+    }
+    chord
+}
+
+fn read_chord(midi: MidiInput) -> Option<Harmony> {
+    let input = read_line("Type the chord's notes like C3 E3 G3, or Enter to play it: ");
+    let notes: BTreeSet<u8> = if input.is_empty() {
+        play_chord(midi)
+    } else {
+        input.split_whitespace().filter_map(parse_note).collect()
+    };
+    if notes.len() < 2 {
+        println!("A chord needs at least two notes.");
+        return None;
+    }
+    Some(Harmony::Chord(chord_shape(&notes)))
+}
+
+fn read_diatonic(current: &Harmony) -> Harmony {
+    let (key, voicing) = match current {
+        Harmony::Diatonic { key, voicing } => (key.clone(), *voicing),
+        Harmony::Chord(_) => ("C major".to_string(), Voicing::Thirds),
+    };
+    let input = read_line(&format!("Key, e.g. Bb or F#m [{}]: ", key));
+    let key = match parse_key(&input) {
+        Some(parsed) => format!("{} {}", tonic_name(parsed), mode_name(parsed.scale)),
+        None => key,
+    };
+
+    for (i, voicing) in Voicing::ALL.iter().enumerate() {
+        println!("  ({}) {}", i, voicing.label());
+    }
+    let voicing = read_line(&format!("Voicing [{}]: ", voicing.label()))
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| Voicing::ALL.get(i).copied())
+        .unwrap_or(voicing);
+    Harmony::Diatonic { key, voicing }
+}
+
+// Set up the harmoniser for live playing from the next session on
+pub fn select_harmony(midi: MidiInput) {
+    let mut prefs = Prefs::load();
+    let current = prefs.harmony.clone();
+    println!(
+        "Harmoniser is {}: {}",
+        if current.enabled { "on" } else { "off" },
+        current.harmony.label()
+    );
+
+    let input = read_line("(c)hord memory, (d)iatonic harmony or (o)ff [keep]: ");
+    prefs.harmony = match input.as_str() {
+        "c" | "chord" => match read_chord(midi) {
+            Some(harmony) => HarmonySettings {
+                enabled: true,
+                harmony,
+            },
+            None => return,
+        },
+        "d" | "diatonic" => HarmonySettings {
+            enabled: true,
+            harmony: read_diatonic(&current.harmony),
+        },
+        "o" | "off" => HarmonySettings {
+            enabled: false,
+            ..current
+        },
+        _ => return,
+    };
+
+    match prefs.save() {
+        Ok(()) if prefs.harmony.enabled => {
+            println!("Harmoniser on: {}", prefs.harmony.harmony.label())
+        }
+        Ok(()) => println!("Harmoniser off"),
+        Err(e) => eprintln!("Failed to save preference: {}", e),
+    }
+}
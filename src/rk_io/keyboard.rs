@@ -0,0 +1,57 @@
+// ---
+use crate::rk_io::user_input::{read_line, read_note};
+use crate::rk_ui::types::{KeyRange, PianoKeyCount};
+use crate::theory::key::note_name;
+use crate::util::prefs::Prefs;
+
+// Choose the keys the piano shows, by size or by its lowest and highest note
+pub fn select_keyboard() {
+    let mut prefs = Prefs::load();
+    let keyboard = prefs.keyboard;
+    println!(
+        "Keyboard: {}{}",
+        keyboard.range.label(),
+        if keyboard.expand {
+            ", widened for notes outside it"
+        } else {
+            ""
+        }
+    );
+    for (i, count) in PianoKeyCount::ALL.iter().enumerate() {
+        let (low, high) = count.range();
+        println!(
+            "  ({}) {} keys, {} - {}",
+            i,
+            *count as u8,
+            note_name(low),
+            note_name(high)
+        );
+    }
+    println!("  (r) range by lowest and highest note");
+
+    let input = read_line("Select size [keep]: ");
+    prefs.keyboard.range = match input.as_str() {
+        "" => keyboard.range,
+        "r" | "range" => {
+            let (low, high) = keyboard.range.notes();
+            let low = read_note("Lowest note", low);
+            let high = read_note("Highest note", high);
+            KeyRange::Notes(low.min(high), high.max(low))
+        }
+        _ => match input
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| PianoKeyCount::ALL.get(i))
+        {
+            Some(count) => KeyRange::Size(*count),
+            None => keyboard.range,
+        },
+    };
+    let input = read_line("Widen for notes played outside it? [Y/n]: ");
+    prefs.keyboard.expand = !matches!(input.as_str(), "n" | "no");
+
+    match prefs.save() {
+        Ok(()) => println!("Keyboard set to {}", prefs.keyboard.range.label()),
+        Err(e) => eprintln!("Failed to save preference: {}", e),
+    }
+}
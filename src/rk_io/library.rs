@@ -0,0 +1,25 @@
+// ---
+use crate::library::index::Library;
+use crate::rk_ui::library_view::run_library;
+use crate::types::recording::recording_dir;
+
+pub fn open_library() {
+    let mut library = Library::load();
+    library.refresh();
+    if library.entries.is_empty() {
+        println!("No recordings found in {}", recording_dir().display());
+        return;
+    }
+    if let Err(e) = library.save() {
+        eprintln!("Failed to write library index: {}", e);
+    }
+
+    match run_library(library) {
+        Ok(library) => {
+            if let Err(e) = library.save() {
+                eprintln!("Failed to write library index: {}", e);
+            }
+        }
+        Err(e) => eprintln!("UI error: {}", e),
+    }
+}
@@ -0,0 +1,88 @@
+use midir::MidiOutput;
+use std::{
+    sync::mpsc::{Sender, channel},
+    thread,
+};
+// ---
+use crate::rk_io::user_input::read_line;
+use crate::types::midi::Message;
+use crate::util::prefs::Prefs;
+
+// The names of the MIDI output ports, in port order
+pub fn list_midi_outputs() -> Vec<String> {
+    let Ok(midi) = MidiOutput::new("rust-keys output") else {
+        return Vec::new();
+    };
+    midi.ports()
+        .iter()
+        .filter_map(|port| midi.port_name(port).ok())
+        .collect()
+}
+
+// Ask for an output port by number, Enter keeps the current one
+pub fn read_midi_out(prompt: &str, current: Option<String>) -> Option<String> {
+    let ports = list_midi_outputs();
+    if ports.is_empty() {
+        println!("No MIDI output ports found.");
+        return None;
+    }
+    println!("MIDI outputs:");
+    for (i, port) in ports.iter().enumerate() {
+        println!("  ({}) {}", i, port);
+    }
+    let input = read_line(&format!(
+        "{}, output number or n for none [{}]: ",
+        prompt,
+        current.as_deref().unwrap_or("none")
+    ));
+    match input.as_str() {
+        "" => current,
+        "n" | "none" => None,
+        _ => input
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| ports.get(i).cloned())
+            .or(current),
+    }
+}
+
+// Choose the port that gets everything played live, harmony included
+pub fn select_midi_thru() {
+    let mut prefs = Prefs::load();
+    prefs.midi_thru = read_midi_out("MIDI thru", prefs.midi_thru.clone());
+    match prefs.save() {
+        Ok(()) => println!(
+            "MIDI thru to {}",
+            prefs.midi_thru.as_deref().unwrap_or("none")
+        ),
+        Err(e) => eprintln!("Failed to save preference: {}", e),
+    }
+}
+
+// Send messages to the named output port from a thread of its own
+pub fn open_midi_out(name: &str) -> Result<Sender<Message>, String> {
+    let midi = MidiOutput::new("rust-keys output").map_err(|e| e.to_string())?;
+    let port = midi
+        .ports()
+        .into_iter()
+        .find(|port| midi.port_name(port).is_ok_and(|n| n == name))
+        .ok_or(format!("No MIDI output \"{}\"", name))?;
+    let mut conn = midi
+        .connect(&port, "rust-keys-out")
+        .map_err(|e| format!("Failed to open {}: {}", name, e))?;
+
+    let (tx, rx) = channel::<Message>();
+    thread::spawn(move || {
+        for (_, data) in rx {
+            let length = match data[0] & 0xf0 {
+                0xc0 | 0xd0 => 2,
+                _ => 3,
+            };
+            if let Err(e) = conn.send(&data[..length]) {
+                eprintln!("MIDI output stopped: {}", e);
+                break;
+            }
+        }
+    });
+    Ok(tx)
+}
@@ -1,6 +1,22 @@
 pub mod user_input;
+pub mod arp;
 pub mod connect;
+pub mod effect_presets;
+pub mod export;
+pub mod harmony;
+pub mod keyboard;
+pub mod library;
+pub mod midi_out;
 pub mod opts;
 pub mod playback;
+pub mod recordings;
+pub mod render_wav;
+pub mod scale;
+pub mod router;
+pub mod smf;
+pub mod tuning;
+pub mod velocity;
+pub mod play_along;
 pub mod watcher;
+pub mod zones;
 pub mod audio_out;
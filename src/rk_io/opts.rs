@@ -1,11 +1,150 @@
-use midir::MidiInputConnection;
+use midir::{MidiInput, MidiInputConnection};
+// ---
+use crate::practice::progress::load_sessions;
+use crate::rk_io::arp::select_arp;
+use crate::rk_io::audio_out::{select_instrument, select_output, select_soundfont};
+use crate::rk_io::effect_presets::select_effect_presets;
+use crate::rk_io::export::select_export;
+use crate::rk_io::harmony::select_harmony;
+use crate::rk_io::keyboard::select_keyboard;
+use crate::rk_io::library::open_library;
+use crate::rk_io::midi_out::select_midi_thru;
+use crate::rk_io::recordings::select_recording;
+use crate::rk_io::render_wav::select_render;
+use crate::rk_io::scale::select_scale;
+use crate::rk_io::tuning::select_tuning;
+use crate::rk_io::user_input::get_input;
+use crate::rk_io::velocity::select_velocity_curve;
+use crate::rk_io::zones::select_zones;
+use crate::rk_ui::progress_view::run_progress;
+use crate::rk_ui::stats_view::run_stats;
+
+#[derive(Clone)]
+enum Opt {
+    Export,
+    Library,
+    Stats,
+    Progress,
+    Render,
+    Soundfont,
+    Instrument,
+    Output,
+    Effects,
+    Velocity,
+    Zones,
+    Tuning,
+    Arp,
+    Harmony,
+    Thru,
+    Keyboard,
+    Scale,
+    Quit,
+}
 
 fn print_opts() {
     println!("Available options:");
-    println!("none");
+    println!("  (e)xport a recording");
+    println!("  (l)ibrary of recordings");
+    println!("  (s)tatistics of a recording");
+    println!("  (p)ractice progress");
+    println!("  (w)av render of a recording");
+    println!("  (f) choose soundfont");
+    println!("  (i)nstrument for live playing");
+    println!("  (o)utput device and buffer");
+    println!("  (x) effect presets");
+    println!("  (v)elocity curve");
+    println!("  (z)ones to split or layer the keyboard");
+    println!("  (t)uning, built-in or Scala");
+    println!("  (a)rpeggiator");
+    println!("  (h)armoniser and chord memory");
+    println!("  (m)idi thru output");
+    println!("  (k)eyboard size shown");
+    println!("  (c) scale highlighting and practice scale");
+    println!("  (q)uit");
+}
+
+fn show_stats() {
+    if let Some((_, recording)) = select_recording()
+        && let Err(e) = run_stats(recording)
+    {
+        eprintln!("UI error: {}", e);
+    }
 }
 
-pub fn select_opt() -> Option<MidiInputConnection<()>> {
+fn show_progress() {
+    let sessions = load_sessions();
+    if sessions.is_empty() {
+        println!("No practice sessions logged yet.");
+        return;
+    }
+    if let Err(e) = run_progress(sessions) {
+        eprintln!("UI error: {}", e);
+    }
+}
+
+pub fn select_opt(midi: MidiInput) -> Option<MidiInputConnection<()>> {
     print_opts();
-    return None;
+    let opt = get_input(
+        "Select option: ",
+        &[
+            ("e", Opt::Export),
+            ("export", Opt::Export),
+            ("l", Opt::Library),
+            ("library", Opt::Library),
+            ("s", Opt::Stats),
+            ("stats", Opt::Stats),
+            ("p", Opt::Progress),
+            ("progress", Opt::Progress),
+            ("w", Opt::Render),
+            ("wav", Opt::Render),
+            ("f", Opt::Soundfont),
+            ("soundfont", Opt::Soundfont),
+            ("i", Opt::Instrument),
+            ("instrument", Opt::Instrument),
+            ("o", Opt::Output),
+            ("output", Opt::Output),
+            ("x", Opt::Effects),
+            ("effects", Opt::Effects),
+            ("v", Opt::Velocity),
+            ("velocity", Opt::Velocity),
+            ("z", Opt::Zones),
+            ("zones", Opt::Zones),
+            ("t", Opt::Tuning),
+            ("tuning", Opt::Tuning),
+            ("a", Opt::Arp),
+            ("arpeggiator", Opt::Arp),
+            ("h", Opt::Harmony),
+            ("harmony", Opt::Harmony),
+            ("m", Opt::Thru),
+            ("midi", Opt::Thru),
+            ("k", Opt::Keyboard),
+            ("keyboard", Opt::Keyboard),
+            ("c", Opt::Scale),
+            ("scale", Opt::Scale),
+            ("q", Opt::Quit),
+            ("quit", Opt::Quit),
+        ],
+    );
+
+    match opt {
+        Some(Opt::Export) => select_export(),
+        Some(Opt::Library) => open_library(),
+        Some(Opt::Stats) => show_stats(),
+        Some(Opt::Progress) => show_progress(),
+        Some(Opt::Render) => select_render(),
+        Some(Opt::Soundfont) => select_soundfont(),
+        Some(Opt::Instrument) => select_instrument(),
+        Some(Opt::Output) => select_output(),
+        Some(Opt::Effects) => select_effect_presets(),
+        Some(Opt::Velocity) => select_velocity_curve(midi),
+        Some(Opt::Zones) => select_zones(),
+        Some(Opt::Tuning) => select_tuning(),
+        Some(Opt::Arp) => select_arp(),
+        Some(Opt::Harmony) => select_harmony(midi),
+        Some(Opt::Thru) => select_midi_thru(),
+        Some(Opt::Keyboard) => select_keyboard(),
+        Some(Opt::Scale) => select_scale(),
+        Some(Opt::Quit) | None => (),
+    }
+    None
 }
@@ -0,0 +1,230 @@
+use midi_player::PlayerController;
+use midir::{MidiInput, MidiInputConnection};
+use std::path::Path;
+// ---
+use crate::practice::piece::{DEFAULT_SPLIT, Hand, HandAssignment, Piece, builtin_pieces};
+use crate::practice::play_along::{OtherHand, PlayAlong, PracticeHands};
+use crate::practice::summary::{RunSummary, previous_runs, write_summary};
+use crate::rk_io::audio_out::{AudioLoop, load_messages, spawn_audio_loop};
+use crate::rk_io::connect::{
+    live_router, log_progress, open_conn, prompt_live_synth, prompt_port, remember_settings,
+    save_recording,
+};
+use crate::rk_io::user_input::read_line;
+use crate::rk_io::watcher::spawn_watcher;
+use crate::rk_ui::types::UiEngine;
+use crate::rk_ui::ui::run_app;
+use crate::theory::key_finding::detect_key;
+use crate::types::midi::Message;
+
+fn select_piece() -> Piece {
+    let mut pieces = builtin_pieces();
+
+    println!("Select index of available pieces, or enter the path of a .mid file:");
+    for (index, piece) in pieces.iter().enumerate() {
+        println!("{} - {}", index, piece.name);
+    }
+
+    loop {
+        let input = read_line("Piece: ");
+        match input.parse::<usize>() {
+            Ok(index) if index < pieces.len() => return pieces.swap_remove(index),
+            Ok(_) => println!(
+                "Invalid selection. Must be a number less than {}.",
+                pieces.len()
+            ),
+            Err(_) => match Piece::from_smf(Path::new(&input)) {
+                Ok(piece) => return piece,
+                Err(e) => println!("Could not load {}: {}", input, e),
+            },
+        }
+    }
+}
+
+fn select_split() -> u8 {
+    loop {
+        let input = read_line(&format!(
+            "Split note, lower notes are the left hand [{}]: ",
+            DEFAULT_SPLIT
+        ));
+        if input.is_empty() {
+            return DEFAULT_SPLIT;
+        }
+        match input.parse::<u8>() {
+            Ok(note) if note < 128 => return note,
+            _ => println!("Invalid note. Must be a MIDI note number 0-127."),
+        }
+    }
+}
+
+fn select_hand_assignment(piece: &Piece) -> HandAssignment {
+    if piece.track_names.len() < 2 {
+        return HandAssignment::Split(select_split());
+    }
+
+    match read_line("Assign hands by (s)plit point or (t)rack [s]: ").as_str() {
+        "t" | "track" => {
+            println!("Tracks:");
+            for (index, (name, count)) in piece
+                .track_names
+                .iter()
+                .zip(piece.track_note_counts())
+                .enumerate()
+            {
+                println!("{} - {} ({} notes)", index, name, count);
+            }
+            let left = read_line("Left hand track numbers, comma separated: ")
+                .split(',')
+                .filter_map(|track| track.trim().parse::<usize>().ok())
+                .collect();
+            HandAssignment::Tracks { left }
+        }
+        _ => HandAssignment::Split(select_split()),
+    }
+}
+
+fn select_hands() -> (PracticeHands, OtherHand) {
+    let hands = match read_line("Practise (b)oth, (l)eft or (r)ight hand [b]: ").as_str() {
+        "l" | "left" => PracticeHands::Only(Hand::Left),
+        "r" | "right" => PracticeHands::Only(Hand::Right),
+        _ => return (PracticeHands::Both, OtherHand::Mute),
+    };
+
+    let other = match read_line("Other hand (m)uted or (a)uto-played [a]: ").as_str() {
+        "m" | "mute" | "muted" => OtherHand::Mute,
+        _ => OtherHand::AutoPlay,
+    };
+
+    (hands, other)
+}
+
+fn select_tempo() -> u32 {
+    loop {
+        let input = read_line("Tempo % [100]: ");
+        if input.is_empty() {
+            return 100;
+        }
+        match input.parse::<u32>() {
+            Ok(percent) if (10..=200).contains(&percent) => return percent,
+            _ => println!("Invalid tempo. Must be between 10 and 200."),
+        }
+    }
+}
+
+// Load the other hand into a player, ready to start with the play-along clock.
+// Loading the soundfont takes a while, so this is done before the clock starts.
+fn load_auto_play(play_along: &PlayAlong) -> Option<(AudioLoop, PlayerController)> {
+    let (audio, mut controller) = match spawn_audio_loop() {
+        Ok(audio) => audio,
+        Err(e) => {
+            eprintln!("Auto-play unavailable: {}", e);
+            return None;
+        }
+    };
+
+    match load_messages(&mut controller, "auto-play", &play_along.auto_play_messages()) {
+        Ok(()) => Some((audio, controller)),
+        Err(e) => {
+            eprintln!("{}", e);
+            audio.stop();
+            None
+        }
+    }
+}
+
+fn print_summary(summary: &RunSummary) {
+    let earlier = previous_runs(&summary.piece);
+
+    match write_summary(summary) {
+        Ok(path) => println!("Summary written to {}", path.display()),
+        Err(e) => eprintln!("Failed to write summary: {}", e),
+    }
+
+    println!(
+        "{} @ {}% ({}): accuracy {:.1}%, best streak {}",
+        summary.piece, summary.tempo_percent, summary.hands, summary.accuracy, summary.best_streak
+    );
+    println!(
+        "  perfect {} | good {} | early {} | late {} | missed {} | wrong {}",
+        summary.counts.perfect,
+        summary.counts.good,
+        summary.counts.early,
+        summary.counts.late,
+        summary.counts.missed,
+        summary.counts.wrong_notes
+    );
+
+    if !earlier.is_empty() {
+        println!("Previous runs:");
+        for run in earlier.iter().rev().take(5) {
+            println!(
+                "  {}: {:.1}% @ {}% ({})",
+                run.started_at, run.accuracy, run.tempo_percent, run.hands
+            );
+        }
+    }
+}
+
+pub fn select_play_along(midi: MidiInput) -> Option<MidiInputConnection<()>> {
+    let mut piece = select_piece();
+    piece.assign_hands(&select_hand_assignment(&piece));
+    let (hands, other_hand) = select_hands();
+    let tempo_percent = select_tempo();
+
+    let ports = midi.ports();
+    let (tx, rx) = spawn_watcher();
+    let index = prompt_port(&midi);
+    let mut engine = UiEngine::new(&piece.name);
+    let mut router = live_router(tx, &mut engine);
+    prompt_live_synth(&mut router, &mut engine);
+
+    let mut play_along = PlayAlong::new(piece, tempo_percent, hands, other_hand);
+    let mut auto_play = match other_hand {
+        OtherHand::AutoPlay => load_auto_play(&play_along),
+        OtherHand::Mute => None,
+    };
+
+    for expected in &play_along.piece.notes {
+        engine.fit_key(expected.note);
+    }
+    // the staff is spelled in the key the piece sounds like
+    let piece_messages: Vec<Message> = play_along
+        .piece
+        .notes
+        .iter()
+        .map(|n| (n.time, [0x90, n.note, n.velocity]))
+        .collect();
+    engine.staff_key = detect_key(&piece_messages);
+
+    // the port, the clock and the other hand start together
+    let conn = open_conn(midi, &ports[index], router);
+    engine.start_clock();
+    play_along.start();
+    if let Some((_, controller)) = auto_play.as_mut() {
+        controller.play();
+    }
+    engine.play_along = Some(play_along);
+
+    let result = run_app(rx, engine);
+    if let Some((audio, mut controller)) = auto_play {
+        controller.stop();
+        audio.stop();
+    }
+
+    match result {
+        Ok(engine) => {
+            save_recording(&engine.recording);
+            let summary = engine.play_along.as_ref().map(|p| p.summary());
+            log_progress(&engine.recording, summary.as_ref());
+            remember_settings(&engine);
+            if let Some(summary) = &summary {
+                print_summary(summary);
+            }
+            Some(conn)
+        }
+        Err(e) => {
+            eprintln!("UI error: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::fs::read_dir;
+use std::path::PathBuf;
+// ---
+use crate::rk_io::user_input::read_line;
+use crate::types::recording::{Recording, recording_dir};
+use crate::util::date::format_timestamp;
+
+fn list_recordings() -> Vec<(PathBuf, Recording)> {
+    let Ok(entries) = read_dir(recording_dir()) else {
+        return Vec::new();
+    };
+
+    let mut recordings: Vec<(PathBuf, Recording)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .filter_map(|path| Recording::load(&path).ok().map(|r| (path, r)))
+        .collect();
+
+    recordings.sort_by_key(|(_, r)| r.recorded_at);
+    recordings
+}
+
+pub fn select_recording() -> Option<(PathBuf, Recording)> {
+    let mut recordings = list_recordings();
+    if recordings.is_empty() {
+        println!("No recordings found in {}", recording_dir().display());
+        return None;
+    }
+
+    println!("Select index of available recordings:");
+    for (index, (_, recording)) in recordings.iter().enumerate() {
+        println!(
+            "{} - {} ({}, {:.1}s)",
+            index,
+            recording.title,
+            format_timestamp(recording.recorded_at),
+            recording.duration() as f64 / 1_000_000.0
+        );
+    }
+
+    match read_line("Recording: ").parse::<usize>() {
+        Ok(index) if index < recordings.len() => Some(recordings.swap_remove(index)),
+        _ => {
+            println!("Invalid selection.");
+            None
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::{fs::File, io::BufWriter};
+// ---
+use crate::audio::{
+    effects::{EffectsHandle, WithEffects},
+    render::render_offline,
+    wav::write_wav,
+};
+use crate::rk_io::audio_out::{create_live_source, load_tuning};
+use crate::rk_io::recordings::select_recording;
+use crate::rk_io::user_input::read_line;
+use crate::util::prefs::Prefs;
+
+const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+// Render a recording to a WAV file beside it, through the chosen instrument
+pub fn select_render() {
+    let Some((path, recording)) = select_recording() else {
+        return;
+    };
+
+    let input = read_line(&format!("Sample rate [{}]: ", DEFAULT_SAMPLE_RATE));
+    let sample_rate = input
+        .parse::<u32>()
+        .ok()
+        .filter(|rate| (8_000..=192_000).contains(rate))
+        .unwrap_or(DEFAULT_SAMPLE_RATE);
+
+    println!(
+        "Rendering through the {} at {} Hz",
+        Prefs::load().instrument.label(),
+        sample_rate
+    );
+    let (source, handle) = match create_live_source(sample_rate, &load_tuning()) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Render failed: {}", e);
+            return;
+        }
+    };
+    let effects = EffectsHandle::new(Prefs::load().effects);
+    let mut source = WithEffects::new(source, effects, sample_rate);
+    let (left, right) = render_offline(
+        &mut source,
+        &handle.events,
+        &recording.messages,
+        sample_rate,
+    );
+
+    let out = path.with_extension("wav");
+    let result = File::create(&out)
+        .and_then(|file| write_wav(&mut BufWriter::new(file), sample_rate, &left, &right));
+    match result {
+        Ok(()) => println!(
+            "Rendered {:.1}s to {}",
+            left.len() as f32 / sample_rate as f32,
+            out.display()
+        ),
+        Err(e) => eprintln!("Render failed: {}", e),
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::mpsc::Sender;
+// ---
+use crate::pipeline::{
+    Stage,
+    transpose::{TransposeHandle, TransposeStage},
+    velocity::VelocityStage,
+};
+use crate::types::midi::Message;
+use crate::util::prefs::Prefs;
+
+// Status bytes from here up are system messages: time code and song position,
+// then real-time clock, start, stop and the like
+const SYSTEM: u8 = 0xf0;
+
+// A listener, with a stage of its own for changes only it should get
+struct Target {
+    sender: Sender<Message>,
+    stage: Option<Box<dyn Stage>>,
+    clocked: bool, // also hears MIDI clock
+}
+
+// Runs incoming messages through the pipeline stages and fans the results out from
+// the MIDI callback, so sound does not wait on the UI
+pub struct Router {
+    stages: Vec<Box<dyn Stage>>,
+    targets: Vec<Target>,
+}
+
+impl Router {
+    // Passes messages on as they are played
+    pub fn new(ui: Sender<Message>) -> Self {
+        Router {
+            stages: Vec::new(),
+            targets: vec![Target {
+                sender: ui,
+                stage: None,
+                clocked: false,
+            }],
+        }
+    }
+
+    // With the stages set up in the preferences, transposed through the handle
+    pub fn from_prefs(ui: Sender<Message>, transpose: TransposeHandle) -> Self {
+        let prefs = Prefs::load();
+        let mut router = Router::new(ui);
+        router.add_stage(Box::new(TransposeStage::new(transpose)));
+        router.add_stage(Box::new(VelocityStage::new(prefs.velocity_curve)));
+        router
+    }
+
+    pub fn add_stage(&mut self, stage: Box<dyn Stage>) {
+        self.stages.push(stage);
+    }
+
+    pub fn add_target(&mut self, target: Sender<Message>) {
+        self.targets.push(Target {
+            sender: target,
+            stage: None,
+            clocked: false,
+        });
+    }
+
+    // A target that hears the pipeline's output through one more stage
+    pub fn add_target_through(&mut self, target: Sender<Message>, stage: Box<dyn Stage>) {
+        self.targets.push(Target {
+            sender: target,
+            stage: Some(stage),
+            clocked: false,
+        });
+    }
+
+    // A target that keeps time with MIDI clock, like a thread from `spawn_clocked`
+    pub fn add_clocked_target(&mut self, target: Sender<Message>) {
+        self.targets.push(Target {
+            sender: target,
+            stage: None,
+            clocked: true,
+        });
+    }
+
+    pub fn send(&mut self, message: Message) {
+        // system messages skip the stages and only go where they are wanted
+        if message.1[0] >= SYSTEM {
+            for target in self.targets.iter().filter(|target| target.clocked) {
+                target.sender.send(message).ok();
+            }
+            return;
+        }
+
+        let mut messages = vec![message];
+        for stage in &mut self.stages {
+            let mut out = Vec::with_capacity(messages.len());
+            for message in messages {
+                stage.process(message, &mut out);
+            }
+            messages = out;
+        }
+
+        let mut out = Vec::new();
+        for target in &mut self.targets {
+            match &mut target.stage {
+                Some(stage) => {
+                    out.clear();
+                    for message in &messages {
+                        stage.process(*message, &mut out);
+                    }
+                    for message in &out {
+                        target.sender.send(*message).ok();
+                    }
+                }
+                None => {
+                    for message in &messages {
+                        target.sender.send(*message).ok();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+// ---
+use crate::rk_io::user_input::read_line;
+use crate::rk_ui::types::ScaleHighlight;
+use crate::theory::key::{mode_name, parse_key, tonic_name};
+use crate::util::prefs::Prefs;
+
+// Choose a scale to improvise over and what the piano highlights, h cycles it live
+pub fn select_scale() {
+    let mut prefs = Prefs::load();
+    let current = prefs
+        .practice_scale
+        .map(|key| format!("{} {}", tonic_name(key), mode_name(key.scale)))
+        .unwrap_or("none".to_string());
+    println!(
+        "Highlighting: {}, practice scale: {}",
+        prefs.scale_highlight.label(),
+        current
+    );
+
+    let input = read_line(&format!(
+        "Scale, e.g. D dorian, Bb or F#m, n for none [{}]: ",
+        current
+    ));
+    match input.as_str() {
+        "" => (),
+        "n" | "none" => prefs.practice_scale = None,
+        _ => match parse_key(&input) {
+            Some(key) => prefs.practice_scale = Some(key),
+            None => println!("Unknown scale \"{}\", keeping {}.", input, current),
+        },
+    }
+
+    let options = [
+        ScaleHighlight::Off,
+        ScaleHighlight::Detected,
+        ScaleHighlight::Chosen,
+    ];
+    for (i, option) in options.iter().enumerate() {
+        println!("  ({}) {}", i, option.label());
+    }
+    let input = read_line(&format!(
+        "Highlight on the piano [{}]: ",
+        prefs.scale_highlight.label()
+    ));
+    if let Some(option) = input.parse::<usize>().ok().and_then(|i| options.get(i)) {
+        prefs.scale_highlight = *option;
+    }
+    if prefs.scale_highlight == ScaleHighlight::Chosen && prefs.practice_scale.is_none() {
+        println!("No scale chosen, highlighting the detected key.");
+        prefs.scale_highlight = ScaleHighlight::Detected;
+    }
+
+    match prefs.save() {
+        Ok(()) => println!("Highlighting {}", prefs.scale_highlight.label()),
+        Err(e) => eprintln!("Failed to save preference: {}", e),
+    }
+}
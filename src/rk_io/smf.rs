@@ -0,0 +1,141 @@
+use std::{error::Error, fs, io, path::Path};
+
+use midly::{
+    Format, Header, MetaMessage, Smf, Timing, TrackEvent, TrackEventKind,
+    live::LiveEvent,
+    num::{u15, u24, u28},
+};
+
+use crate::types::midi::Message;
+
+// Ticks per quarter note used when writing files
+const WRITE_PPQ: u16 = 480;
+// 120 bpm, in micro seconds per quarter note
+const DEFAULT_TEMPO: u32 = 500_000;
+
+pub struct SmfFile {
+    pub track_names: Vec<String>,
+    // (track, message) with timestamps in micro seconds from the start of the file
+    pub messages: Vec<(usize, Message)>,
+}
+
+// Note and controller messages are kept, everything else is dropped
+fn to_message_data(kind: &TrackEventKind) -> Option<[u8; 3]> {
+    let live = kind.as_live_event()?;
+    let mut bytes = Vec::new();
+    live.write_std(&mut bytes).ok()?;
+
+    match bytes.as_slice() {
+        [status @ 0x80..=0xbf, data1, data2] => Some([*status, *data1, *data2]),
+        _ => None,
+    }
+}
+
+pub fn read_smf(path: &Path) -> Result<SmfFile, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    let smf = Smf::parse(&bytes)?;
+
+    // Tempo changes apply across all tracks: (tick, micro seconds per quarter)
+    let mut tempo_map: Vec<(u64, u32)> = Vec::new();
+    for track in &smf.tracks {
+        let mut tick = 0u64;
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            if let TrackEventKind::Meta(MetaMessage::Tempo(tempo)) = event.kind {
+                tempo_map.push((tick, tempo.as_int()));
+            }
+        }
+    }
+    tempo_map.sort_by_key(|(tick, _)| *tick);
+
+    let tick_to_micros = |tick: u64| -> u64 {
+        match smf.header.timing {
+            Timing::Metrical(ppq) => {
+                let ppq = ppq.as_int().max(1) as u64;
+                let mut micros = 0u64;
+                let mut last_tick = 0u64;
+                let mut tempo = DEFAULT_TEMPO as u64;
+                for (change_tick, change_tempo) in tempo_map.iter().filter(|(t, _)| *t < tick) {
+                    micros += (change_tick - last_tick) * tempo / ppq;
+                    last_tick = *change_tick;
+                    tempo = *change_tempo as u64;
+                }
+                micros + (tick - last_tick) * tempo / ppq
+            }
+            Timing::Timecode(fps, subframe) => {
+                let ticks_per_second = (fps.as_f32() * subframe as f32).max(1.0);
+                (tick as f64 * 1_000_000.0 / ticks_per_second as f64) as u64
+            }
+        }
+    };
+
+    let mut track_names = Vec::new();
+    let mut messages = Vec::new();
+
+    for (index, track) in smf.tracks.iter().enumerate() {
+        let mut tick = 0u64;
+        let mut name = format!("Track {}", index);
+
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            match &event.kind {
+                TrackEventKind::Meta(MetaMessage::TrackName(bytes)) => {
+                    name = String::from_utf8_lossy(bytes).trim().to_string();
+                }
+                kind => {
+                    if let Some(data) = to_message_data(kind) {
+                        messages.push((index, (tick_to_micros(tick), data)));
+                    }
+                }
+            }
+        }
+
+        track_names.push(name);
+    }
+
+    // stable sort keeps the order of events sharing a timestamp
+    messages.sort_by_key(|(_, (time, _))| *time);
+
+    Ok(SmfFile {
+        track_names,
+        messages,
+    })
+}
+
+// Write messages to a single track file at 120 bpm.
+// Timestamps are micro seconds, the first message is not moved to zero.
+pub fn write_smf(path: &Path, messages: &[Message]) -> io::Result<()> {
+    let mut sorted = messages.to_vec();
+    sorted.sort_by_key(|(time, _)| *time);
+
+    let mut track: Vec<TrackEvent> = vec![TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(DEFAULT_TEMPO))),
+    }];
+
+    let arena = midly::Arena::new();
+    let mut last_tick = 0u64;
+    for (time, data) in &sorted {
+        let Ok(live) = LiveEvent::parse(data) else {
+            continue;
+        };
+        let tick = time * WRITE_PPQ as u64 / DEFAULT_TEMPO as u64;
+        track.push(TrackEvent {
+            delta: u28::new((tick - last_tick) as u32),
+            kind: live.as_track_event(&arena),
+        });
+        last_tick = tick;
+    }
+
+    track.push(TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    });
+
+    let mut smf = Smf::new(Header {
+        format: Format::SingleTrack,
+        timing: Timing::Metrical(u15::new(WRITE_PPQ)),
+    });
+    smf.tracks.push(track);
+    smf.save(path)
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+// ---
+use crate::rk_io::user_input::{get_input, read_line};
+use crate::theory::tuning::{Temperament, TuningChoice};
+use crate::util::prefs::Prefs;
+
+// A .scl file and optionally a .kbm to map it to the keys
+fn read_scala() -> Option<TuningChoice> {
+    let scale = read_line("Path to the .scl file: ");
+    if scale.is_empty() {
+        return None;
+    }
+    let mapping = read_line("Path to a .kbm file [linear from middle C]: ");
+    Some(TuningChoice::Scala {
+        scale: PathBuf::from(scale),
+        mapping: (!mapping.is_empty()).then(|| PathBuf::from(mapping)),
+    })
+}
+
+// Choose the tuning the synth plays in from the next session on
+pub fn select_tuning() {
+    let mut prefs = Prefs::load();
+    let mut choices = vec![TuningChoice::Equal];
+    choices.extend(Temperament::ALL.into_iter().map(TuningChoice::Builtin));
+
+    println!("Tunings (current: {}):", prefs.tuning.label());
+    for (i, choice) in choices.iter().enumerate() {
+        println!("  ({}) {}", i, choice.label());
+    }
+    println!("  (s) Scala file");
+
+    let keys: Vec<String> = (0..choices.len()).map(|i| i.to_string()).collect();
+    let mut options: Vec<(&str, Option<TuningChoice>)> = keys
+        .iter()
+        .map(String::as_str)
+        .zip(choices.into_iter().map(Some))
+        .collect();
+    options.push(("s", None));
+    options.push(("scala", None));
+
+    let choice = match get_input("Select tuning: ", &options) {
+        Some(Some(choice)) => choice,
+        Some(None) => match read_scala() {
+            Some(choice) => choice,
+            None => return,
+        },
+        None => return,
+    };
+
+    // check the files now rather than at the start of a session
+    match choice.load() {
+        Ok(tuning) => {
+            let a4 = tuning.frequency(69).map(|f| format!("{:.2} Hz", f));
+            println!(
+                "{}, A4 at {}",
+                tuning.name,
+                a4.unwrap_or("no pitch".to_string())
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to load tuning: {}", e);
+            return;
+        }
+    }
+
+    prefs.tuning = choice;
+    match prefs.save() {
+        Ok(()) => println!("Tuning set to {}", prefs.tuning.label()),
+        Err(e) => eprintln!("Failed to save preference: {}", e),
+    }
+}
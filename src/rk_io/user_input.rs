@@ -1,4 +1,6 @@
 use std::io::{Write, stdin, stdout};
+// ---
+use crate::theory::key::{note_name, parse_note};
 
 pub fn get_input<T: Clone>(prompt: &str, options: &[(&str, T)]) -> Option<T> {
     let mut input = String::new();
@@ -19,6 +21,28 @@ pub fn get_input<T: Clone>(prompt: &str, options: &[(&str, T)]) -> Option<T> {
     }
 }
 
+pub fn read_line(prompt: &str) -> String {
+    let mut input = String::new();
+    print!("{}", prompt);
+    stdout().flush().unwrap();
+    stdin().read_line(&mut input).unwrap();
+    input.trim().to_string()
+}
+
+// Ask for a key until one parses, Enter takes the default
+pub fn read_note(prompt: &str, default: u8) -> u8 {
+    loop {
+        let input = read_line(&format!("{} [{}]: ", prompt, note_name(default)));
+        if input.is_empty() {
+            return default;
+        }
+        match parse_note(&input) {
+            Some(note) => return note,
+            None => println!("Enter a key like C3, F#2 or a MIDI number."),
+        }
+    }
+}
+
 pub fn pause_for_enter() {
     let mut input = String::new();
     let mut stdout = stdout();
@@ -0,0 +1,29 @@
+use midir::MidiInput;
+use std::sync::mpsc::channel;
+// ---
+use crate::rk_io::connect::{open_conn, prompt_port};
+use crate::rk_io::router::Router;
+use crate::rk_ui::velocity_view::run_velocity_editor;
+use crate::util::prefs::Prefs;
+
+// Edit the velocity curve while playing, previewing raw velocities against it
+pub fn select_velocity_curve(midi: MidiInput) {
+    let index = prompt_port(&midi);
+    let ports = midi.ports();
+    let (tx, rx) = channel();
+    // no stages, the editor needs the velocities as played
+    let _conn = open_conn(midi, &ports[index], Router::new(tx));
+
+    let mut prefs = Prefs::load();
+    match run_velocity_editor(prefs.velocity_curve.clone(), rx) {
+        Ok(Some(curve)) => {
+            prefs.velocity_curve = curve;
+            match prefs.save() {
+                Ok(()) => println!("Velocity curve set to {}", prefs.velocity_curve.label()),
+                Err(e) => eprintln!("Failed to save preference: {}", e),
+            }
+        }
+        Ok(None) => (),
+        Err(e) => eprintln!("UI error: {}", e),
+    }
+}
@@ -0,0 +1,107 @@
+// ---
+use crate::audio::soundfont::{default_soundfont, list_presets};
+use crate::pipeline::zones::Zone;
+use crate::rk_io::user_input::{read_line, read_note};
+use crate::util::prefs::Prefs;
+
+// A preset from the soundfont's list by number, or "bank:program"
+fn read_preset() -> (u8, u8) {
+    let presets = default_soundfont()
+        .and_then(|path| list_presets(&path))
+        .unwrap_or_default();
+    for (i, preset) in presets.iter().enumerate() {
+        println!("  ({}) {}:{} {}", i, preset.bank, preset.patch, preset.name);
+    }
+
+    let input = read_line("Preset number or bank:program [0:0]: ");
+    if let Some((bank, program)) = input.split_once(':') {
+        return (
+            bank.trim().parse::<u8>().unwrap_or(0).min(127),
+            program.trim().parse::<u8>().unwrap_or(0).min(127),
+        );
+    }
+    input
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| presets.get(i))
+        .map(|p| (p.bank.clamp(0, 127) as u8, p.patch.clamp(0, 127) as u8))
+        .unwrap_or((0, 0))
+}
+
+fn read_zone(index: usize) -> Option<Zone> {
+    let name = read_line(&format!("Zone name [zone {}]: ", index + 1));
+    let name = match name.is_empty() {
+        true => format!("zone {}", index + 1),
+        false => name,
+    };
+    let low = read_note("Lowest key", 0);
+    let high = read_note("Highest key", 127);
+    if low > high {
+        println!("The lowest key is above the highest.");
+        return None;
+    }
+    let channel = read_line(&format!("Channel 1-16 [{}]: ", index + 1))
+        .parse::<u8>()
+        .unwrap_or(index as u8 + 1)
+        .clamp(1, 16)
+        - 1;
+    let (bank, program) = read_preset();
+    let transpose = read_line("Transpose in semitones [0]: ")
+        .parse::<i8>()
+        .unwrap_or(0)
+        .clamp(-48, 48);
+
+    Some(Zone {
+        name,
+        low,
+        high,
+        channel,
+        bank,
+        program,
+        transpose,
+    })
+}
+
+// Split or layer the keyboard over several presets. The zones apply to the live synth.
+pub fn select_zones() {
+    let mut prefs = Prefs::load();
+    if prefs.zones.is_empty() {
+        println!("No zones, every key plays the instrument on channel 1.");
+    } else {
+        println!("Zones:");
+        for (i, zone) in prefs.zones.iter().enumerate() {
+            println!("  ({}) {}", i, zone.label());
+        }
+    }
+
+    match read_line("(a)dd, (d)elete or (c)lear zones: ").as_str() {
+        "a" | "add" => {
+            let Some(zone) = read_zone(prefs.zones.len()) else {
+                return;
+            };
+            println!("Added {}", zone.label());
+            prefs.zones.push(zone);
+        }
+        "d" | "delete" => {
+            let input = read_line("Zone number: ");
+            match input.parse::<usize>() {
+                Ok(i) if i < prefs.zones.len() => {
+                    println!("Deleted {}", prefs.zones.remove(i).name);
+                }
+                _ => {
+                    println!("No zone \"{}\"", input);
+                    return;
+                }
+            }
+        }
+        "c" | "clear" => {
+            prefs.zones.clear();
+            println!("Cleared zones");
+        }
+        _ => return,
+    }
+
+    if let Err(e) = prefs.save() {
+        eprintln!("Failed to save preference: {}", e);
+    }
+}
@@ -0,0 +1,278 @@
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use midi_player::PlayerController;
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    prelude::Color,
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+};
+use std::{error::Error, time::Duration};
+// ---
+use crate::library::index::{Library, SortBy};
+use crate::rk_io::audio_out::{AudioLoop, load_messages, spawn_audio_loop};
+use crate::types::recording::Recording;
+use crate::util::date::format_timestamp;
+
+enum Mode {
+    Browse,
+    Search,
+    Tags(String),
+}
+
+struct LibraryView {
+    library: Library,
+    query: String,
+    sort: SortBy,
+    mode: Mode,
+    table: TableState,
+    preview: Option<(AudioLoop, PlayerController)>,
+    status: String,
+}
+
+impl LibraryView {
+    fn visible(&self) -> Vec<usize> {
+        self.library.search(&self.query, self.sort)
+    }
+
+    fn selected(&self) -> Option<usize> {
+        let visible = self.visible();
+        self.table.selected().and_then(|i| visible.get(i).copied())
+    }
+
+    fn clamp_selection(&mut self) {
+        let count = self.visible().len();
+        match self.table.selected() {
+            _ if count == 0 => self.table.select(None),
+            Some(i) if i >= count => self.table.select(Some(count - 1)),
+            None => self.table.select(Some(0)),
+            _ => (),
+        }
+    }
+
+    fn play_selected(&mut self) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        let entry = &self.library.entries[index];
+        let recording = match Recording::load(&entry.path()) {
+            Ok(recording) => recording,
+            Err(e) => {
+                self.status = format!("Could not load {}: {}", entry.file, e);
+                return;
+            }
+        };
+
+        // one audio thread is kept for the whole browser session
+        if self.preview.is_none() {
+            match spawn_audio_loop() {
+                Ok(preview) => self.preview = Some(preview),
+                Err(e) => {
+                    self.status = format!("Preview unavailable: {}", e);
+                    return;
+                }
+            }
+        }
+
+        if let Some((_, controller)) = self.preview.as_mut() {
+            controller.stop();
+            match load_messages(controller, "preview", &recording.messages) {
+                Ok(()) => {
+                    controller.play();
+                    self.status = format!("Playing {}", recording.title);
+                }
+                Err(e) => self.status = e,
+            }
+        }
+    }
+
+    fn stop_preview(&mut self) {
+        if let Some((_, controller)) = self.preview.as_mut() {
+            controller.stop();
+            self.status = "Stopped".to_string();
+        }
+    }
+
+    // Returns true when the browser should close
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match &mut self.mode {
+            Mode::Search => match code {
+                KeyCode::Esc | KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Char(c) => self.query.push(c),
+                _ => (),
+            },
+            Mode::Tags(text) => match code {
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Enter => {
+                    let tags = text
+                        .split(',')
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect();
+                    if let Some(index) = self.selected() {
+                        self.library.entries[index].tags = tags;
+                    }
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => (),
+            },
+            Mode::Browse => {
+                self.status.clear();
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc => return true,
+                    KeyCode::Char('/') => self.mode = Mode::Search,
+                    KeyCode::Char('s') => self.sort = self.sort.next(),
+                    KeyCode::Char('t') => {
+                        if let Some(index) = self.selected() {
+                            self.mode = Mode::Tags(self.library.entries[index].tags.join(", "));
+                        }
+                    }
+                    KeyCode::Char('p') | KeyCode::Enter => self.play_selected(),
+                    KeyCode::Char('x') => self.stop_preview(),
+                    KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                    _ => (),
+                }
+            }
+        }
+
+        self.clamp_selection();
+        false
+    }
+}
+
+fn format_duration(micros: u64) -> String {
+    let seconds = micros / 1_000_000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn render_search(f: &mut Frame, view: &LibraryView, area: Rect) {
+    let style = match view.mode {
+        Mode::Search => Style::default().fg(Color::Yellow),
+        _ => Style::default(),
+    };
+    let block = Block::default()
+        .title(format!(" Search - sorted by {} ", view.sort.label()))
+        .borders(Borders::ALL)
+        .border_style(style);
+    f.render_widget(Paragraph::new(view.query.as_str()).block(block), area);
+}
+
+fn render_table(f: &mut Frame, view: &mut LibraryView, area: Rect) {
+    let rows: Vec<Row> = view
+        .visible()
+        .into_iter()
+        .map(|index| {
+            let entry = &view.library.entries[index];
+            Row::new(vec![
+                Cell::from(entry.title.clone()),
+                Cell::from(format_timestamp(entry.recorded_at)),
+                Cell::from(format_duration(entry.duration)),
+                Cell::from(entry.note_count.to_string()),
+                Cell::from(entry.key_label()),
+                Cell::from(entry.tags.join(", ")),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Length(17),
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Length(12),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["Title", "Recorded", "Length", "Notes", "Key", "Tags"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::default().bg(Color::DarkGray))
+    .block(
+        Block::default()
+            .title(format!(" Library ({}) ", view.library.entries.len()))
+            .borders(Borders::ALL),
+    );
+
+    f.render_stateful_widget(table, area, &mut view.table);
+}
+
+fn render_footer(f: &mut Frame, view: &LibraryView, area: Rect) {
+    let line = match &view.mode {
+        Mode::Tags(text) => format!("Tags (comma separated): {}", text),
+        Mode::Search => "Type to search title, key or tags - Enter to finish".to_string(),
+        Mode::Browse if !view.status.is_empty() => view.status.clone(),
+        Mode::Browse => "/ search | s sort | t tags | p play | x stop | q quit".to_string(),
+    };
+    f.render_widget(Paragraph::new(Line::from(line)), area);
+}
+
+fn ui(f: &mut Frame, view: &mut LibraryView) {
+    let chunks = Layout::vertical([
+        Constraint::Length(3), // Search
+        Constraint::Fill(1),   // Recordings
+        Constraint::Length(1), // Help or status
+    ])
+    .split(f.area());
+
+    render_search(f, view, chunks[0]);
+    render_table(f, view, chunks[1]);
+    render_footer(f, view, chunks[2]);
+}
+
+// Browse the library until closed, returning it with any tag edits
+pub fn run_library(library: Library) -> Result<Library, Box<dyn Error>> {
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut view = LibraryView {
+        library,
+        query: String::new(),
+        sort: SortBy::Date,
+        mode: Mode::Browse,
+        table: TableState::default(),
+        preview: None,
+        status: String::new(),
+    };
+    view.clamp_selection();
+
+    loop {
+        terminal.draw(|f| ui(f, &mut view))?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && view.handle_key(key.code)
+        {
+            break;
+        }
+    }
+
+    // the audio thread ends with the browser
+    if let Some((audio, mut controller)) = view.preview.take() {
+        controller.stop();
+        audio.stop();
+    }
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(view.library)
+}
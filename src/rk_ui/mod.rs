@@ -1,7 +1,15 @@
 pub mod types;
 pub mod ui;
+pub mod render_effects;
 pub mod render_piano;
+pub mod render_stats;
+pub mod progress_view;
+pub mod soundfont_view;
+pub mod stats_view;
 pub mod piano_key_widget;
+pub mod staff_widget;
 pub mod ui_engine;
 pub mod util;
+pub mod library_view;
+pub mod velocity_view;
 pub mod constants;
\ No newline at end of file
@@ -0,0 +1,239 @@
+use crossterm::{
+    event::{self, Event, KeyCode},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    prelude::Color,
+    style::{Modifier, Style},
+    symbols::Marker,
+    text::Line,
+    widgets::{
+        Axis, Block, Borders, Cell, Chart, Dataset, GraphType, Paragraph, Row, Sparkline, Table,
+    },
+};
+use std::{
+    error::Error,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+// ---
+use crate::practice::progress::{SessionLog, WeekTotal, daily_totals, weekly_totals};
+use crate::util::date::format_timestamp;
+
+// Days shown in the daily sparkline
+const SPARKLINE_DAYS: usize = 30;
+// Weeks listed in the table
+const TABLE_WEEKS: usize = 8;
+
+struct Progress {
+    sessions: Vec<SessionLog>,
+    weeks: Vec<WeekTotal>,
+    daily: Vec<u64>,
+}
+
+fn date(timestamp: u64) -> String {
+    format_timestamp(timestamp)[..10].to_string()
+}
+
+fn render_weekly_chart(f: &mut Frame, progress: &Progress, area: Rect) {
+    let points: Vec<(f64, f64)> = progress
+        .weeks
+        .iter()
+        .enumerate()
+        .map(|(i, week)| (i as f64, week.duration as f64 / 60.0))
+        .collect();
+    let max = points.iter().map(|(_, y)| *y).fold(10.0, f64::max);
+    let last = (points.len().max(2) - 1) as f64;
+
+    let first_label = progress.weeks.first().map(|w| date(w.week_start));
+    let last_label = progress.weeks.last().map(|w| date(w.week_start));
+
+    let chart = Chart::new(vec![
+        Dataset::default()
+            .name("minutes")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&points),
+    ])
+    .block(
+        Block::default()
+            .title(" Minutes per week ")
+            .borders(Borders::ALL),
+    )
+    .x_axis(Axis::default().bounds([0.0, last]).labels(vec![
+        first_label.unwrap_or_default(),
+        last_label.unwrap_or_default(),
+    ]))
+    .y_axis(
+        Axis::default()
+            .bounds([0.0, max])
+            .labels(vec!["0".to_string(), format!("{:.0}", max)]),
+    );
+
+    f.render_widget(chart, area);
+}
+
+fn render_accuracy_chart(f: &mut Frame, progress: &Progress, area: Rect) {
+    let points: Vec<(f64, f64)> = progress
+        .sessions
+        .iter()
+        .filter_map(|s| s.accuracy)
+        .enumerate()
+        .map(|(i, accuracy)| (i as f64, accuracy as f64))
+        .collect();
+    let last = (points.len().max(2) - 1) as f64;
+
+    let chart = Chart::new(vec![
+        Dataset::default()
+            .name("accuracy %")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Green))
+            .data(&points),
+    ])
+    .block(
+        Block::default()
+            .title(format!(" Play-along accuracy ({} runs) ", points.len()))
+            .borders(Borders::ALL),
+    )
+    .x_axis(Axis::default().bounds([0.0, last]))
+    .y_axis(
+        Axis::default()
+            .bounds([0.0, 100.0])
+            .labels(vec!["0", "50", "100"]),
+    );
+
+    f.render_widget(chart, area);
+}
+
+fn render_daily(f: &mut Frame, progress: &Progress, area: Rect) {
+    let minutes: Vec<u64> = progress.daily.iter().map(|s| s.div_ceil(60)).collect();
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .title(format!(
+                    " Last {} days - {} minutes ",
+                    SPARKLINE_DAYS,
+                    minutes.iter().sum::<u64>()
+                ))
+                .borders(Borders::ALL),
+        )
+        .data(&minutes)
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(sparkline, area);
+}
+
+fn render_weeks(f: &mut Frame, progress: &Progress, area: Rect) {
+    let rows: Vec<Row> = progress
+        .weeks
+        .iter()
+        .rev()
+        .take(TABLE_WEEKS)
+        .map(|week| {
+            Row::new(vec![
+                Cell::from(date(week.week_start)),
+                Cell::from(week.sessions.to_string()),
+                Cell::from(format!("{}", week.duration / 60)),
+                Cell::from(week.note_count.to_string()),
+                Cell::from(
+                    week.best_accuracy
+                        .map(|a| format!("{:.1}%", a))
+                        .unwrap_or("-".to_string()),
+                ),
+                Cell::from(
+                    week.top_tempo
+                        .map(|t| format!("{}%", t))
+                        .unwrap_or("-".to_string()),
+                ),
+                Cell::from(week.pieces.join(", ")),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(11),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(6),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Week", "Sessions", "Minutes", "Notes", "Accuracy", "Tempo", "Pieces",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .title(" Weekly totals ")
+            .borders(Borders::ALL),
+    );
+
+    f.render_widget(table, area);
+}
+
+fn ui(f: &mut Frame, progress: &Progress) {
+    let chunks = Layout::vertical([
+        Constraint::Length(1),      // Title
+        Constraint::Percentage(40), // Charts
+        Constraint::Length(5),      // Daily sparkline
+        Constraint::Fill(1),        // Weekly table
+    ])
+    .split(f.area());
+    let charts = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
+
+    f.render_widget(
+        Paragraph::new(Line::from(format!(
+            "Practice progress - {} sessions - press q to close",
+            progress.sessions.len()
+        ))),
+        chunks[0],
+    );
+    render_weekly_chart(f, progress, charts[0]);
+    render_accuracy_chart(f, progress, charts[1]);
+    render_daily(f, progress, chunks[2]);
+    render_weeks(f, progress, chunks[3]);
+}
+
+pub fn run_progress(sessions: Vec<SessionLog>) -> Result<(), Box<dyn Error>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let progress = Progress {
+        weeks: weekly_totals(&sessions),
+        daily: daily_totals(&sessions, now, SPARKLINE_DAYS),
+        sessions,
+    };
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        terminal.draw(|f| ui(f, &progress))?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            break;
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use ratatui::{
+    Frame,
+    layout::Rect,
+    prelude::Color,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::audio::effects::{EffectSettings, Param};
+
+// Width of the bar showing where a value sits in its range
+const BAR_WIDTH: usize = 20;
+
+fn value_bar(param: Param, value: f32) -> String {
+    let (min, max, _) = param.range();
+    let filled = (((value - min) / (max - min)) * BAR_WIDTH as f32).round() as usize;
+    format!(
+        "{}{}",
+        "█".repeat(filled.min(BAR_WIDTH)),
+        "░".repeat(BAR_WIDTH - filled.min(BAR_WIDTH))
+    )
+}
+
+pub fn render(
+    f: &mut Frame,
+    settings: &EffectSettings,
+    selected: usize,
+    preset: Option<&str>,
+    area: Rect,
+) {
+    let mut lines = Vec::new();
+    let mut current = None;
+
+    for (index, param) in Param::ALL.iter().enumerate() {
+        let effect = param.effect();
+        let enabled = settings.enabled(effect);
+        if current != Some(effect) {
+            current = Some(effect);
+            let (state, color) = if enabled {
+                ("on", Color::Green)
+            } else {
+                ("off", Color::DarkGray)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{:<8}", effect.label()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(state, Style::default().fg(color)),
+            ]));
+        }
+
+        let value = settings.value(*param);
+        let style = match (index == selected, enabled) {
+            (true, _) => Style::default().bg(Color::DarkGray),
+            (false, true) => Style::default(),
+            (false, false) => Style::default().fg(Color::DarkGray),
+        };
+        lines.push(Line::styled(
+            format!(
+                "  {:<10} {:>7.2} {:<3} {}",
+                param.label(),
+                value,
+                param.unit(),
+                value_bar(*param, value)
+            ),
+            style,
+        ));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(
+        "up/down select | left/right adjust | space on/off | [ ] presets | e close",
+    ));
+
+    let title = match preset {
+        Some(name) => format!(" Effects - {} ", name),
+        None => " Effects ".to_string(),
+    };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
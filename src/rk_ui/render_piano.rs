@@ -1,17 +1,30 @@
 use ratatui::{
     Frame,
     layout::Rect,
-    widgets::{Block, Borders},
+    style::{Color, Style},
+    widgets::{Block, Borders, Paragraph},
 };
 
+use crate::pipeline::zones::Zone;
 use crate::rk_ui::{
     constants::{KEY_NAMES, PIANO_PATTERN},
-    types::{KeyContext, NoteContext, RenderContext, UiEngine},
-    util::{count_white_keys_in_range, get_key_colors},
+    types::{KeyContext, NoteContext, RenderContext, ScaleHighlight, UiEngine},
+    util::{count_white_keys_in_range, get_key_colors, heat_color},
 };
+use crate::theory::key::{key_name, mode_name, tonic_name};
 
 use super::types::PianoKey;
 
+// Zone bands take these in turn
+const ZONE_COLORS: [Color; 6] = [
+    Color::Blue,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Cyan,
+    Color::Red,
+];
+
 // Store white key positions for black key placement
 #[derive(Clone)]
 struct WhiteKeyPosition {
@@ -21,7 +34,40 @@ struct WhiteKeyPosition {
 }
 
 pub fn render(f: &mut Frame, engine: &mut UiEngine, area: Rect, start_note: u8, end_note: u8) {
-    let block = Block::default().title(" Piano ").borders(Borders::ALL);
+    let mut title = " Piano ".to_string();
+    if let Some((sounding, polyphony)) = engine.synth.as_ref().and_then(|s| s.voices()) {
+        title.push_str(&format!("- {}/{} voices ", sounding, polyphony));
+    }
+    if let Some(output) = &engine.output {
+        title.push_str(&format!("- {} ", output));
+    }
+    if let Some(transpose) = &engine.transpose
+        && transpose.get() != Default::default()
+    {
+        title.push_str(&format!("- {} ", transpose.get().label()));
+    }
+    if let Some(tuning) = &engine.tuning {
+        title.push_str(&format!("- {} ", tuning));
+    }
+    if let Some(arp) = &engine.arp {
+        title.push_str(&format!("- arp {} ", arp));
+    }
+    match (
+        engine.scale_highlight,
+        engine.recent_key,
+        engine.practice_scale,
+    ) {
+        (ScaleHighlight::Detected, Some((key, fit)), _) => {
+            title.push_str(&format!("- key {} ({:.0}%) ", key_name(key), fit * 100.0))
+        }
+        (ScaleHighlight::Chosen, _, Some(key)) => title.push_str(&format!(
+            "- scale {} {} ",
+            tonic_name(key),
+            mode_name(key.scale)
+        )),
+        _ => (),
+    }
+    let block = Block::default().title(title).borders(Borders::ALL);
     let inner_area = block.inner(area);
     f.render_widget(block, area);
 
@@ -43,7 +89,9 @@ pub fn render(f: &mut Frame, engine: &mut UiEngine, area: Rect, start_note: u8,
     // Use remainder as left padding to center the keyboard
     let left_padding = width_remainder / 2;
 
-    let white_key_height = inner_area.height;
+    // a row under the keys for each zone, as long as the keys keep most of the height
+    let zone_rows = (engine.zones.len() as u16).min(inner_area.height / 3);
+    let white_key_height = inner_area.height - zone_rows;
     let black_key_height = white_key_height * 2 / 3;
 
     // Collect white key positions during first pass
@@ -67,7 +115,15 @@ pub fn render(f: &mut Frame, engine: &mut UiEngine, area: Rect, start_note: u8,
 
             draw_white_key(
                 f,
-                &NoteContext { octave, is_active },
+                &NoteContext {
+                    octave,
+                    is_active,
+                    is_played: engine.key_played(note),
+                    is_generated: engine.key_generated(note),
+                    is_expected: engine.key_expected(note),
+                    in_scale: engine.key_in_scale(note),
+                    heat: engine.key_heat(note),
+                },
                 &KeyContext {
                     key_index,
                     key_x: white_key_x,
@@ -97,7 +153,15 @@ pub fn render(f: &mut Frame, engine: &mut UiEngine, area: Rect, start_note: u8,
             {
                 draw_black_key(
                     f,
-                    &NoteContext { octave, is_active },
+                    &NoteContext {
+                        octave,
+                        is_active,
+                        is_played: engine.key_played(note),
+                        is_generated: engine.key_generated(note),
+                        is_expected: engine.key_expected(note),
+                        in_scale: engine.key_in_scale(note),
+                        heat: engine.key_heat(note),
+                    },
                     &KeyContext {
                         key_index,
                         key_x: black_key_x,
@@ -112,6 +176,18 @@ pub fn render(f: &mut Frame, engine: &mut UiEngine, area: Rect, start_note: u8,
             }
         }
     }
+
+    draw_zone_bands(
+        f,
+        &engine.zones[..zone_rows as usize],
+        (start_note, end_note),
+        &white_key_positions,
+        Rect {
+            y: inner_area.y + white_key_height,
+            height: zone_rows,
+            ..inner_area
+        },
+    );
 }
 
 fn calculate_black_key_position(
@@ -167,6 +243,66 @@ fn calculate_black_key_position(
     }
 }
 
+// Left and right edge of a key
+fn key_span(note: u8, white_positions: &[WhiteKeyPosition]) -> Option<(u16, u16)> {
+    match white_positions.iter().find(|pos| pos.note == note) {
+        Some(pos) => Some((pos.x, pos.x + pos.width)),
+        None => calculate_black_key_position(note, white_positions).map(|(x, w)| (x, x + w)),
+    }
+}
+
+// One coloured row per zone, spanning its keys
+fn draw_zone_bands(
+    f: &mut Frame,
+    zones: &[Zone],
+    (start_note, end_note): (u8, u8),
+    white_positions: &[WhiteKeyPosition],
+    area: Rect,
+) {
+    for (index, zone) in zones.iter().enumerate() {
+        let (low, high) = (zone.low.max(start_note), zone.high.min(end_note));
+        if low > high {
+            continue;
+        }
+        let (Some((left, _)), Some((_, right))) = (
+            key_span(low, white_positions),
+            key_span(high, white_positions),
+        ) else {
+            continue;
+        };
+
+        let band = Rect {
+            x: area.x + left,
+            y: area.y + index as u16,
+            width: right.saturating_sub(left).max(1),
+            height: 1,
+        };
+        let color = ZONE_COLORS[index % ZONE_COLORS.len()];
+        f.render_widget(
+            Paragraph::new(zone.name.as_str()).style(Style::default().bg(color).fg(Color::Black)),
+            band,
+        );
+    }
+}
+
+// Played keys take a heatmap colour while statistics are shown. Pressed keys that
+// sound elsewhere because of the transpose, and notes the harmoniser adds, are
+// picked out. While playing along, keys to hold show whether they are held. Keys
+// in a highlighted scale are tinted.
+fn key_colors(is_white: bool, note_ctx: &NoteContext) -> (Color, Color) {
+    match note_ctx.heat {
+        _ if note_ctx.is_expected && note_ctx.is_active => (Color::Green, Color::Black),
+        _ if note_ctx.is_expected => (Color::LightBlue, Color::Black),
+        _ if note_ctx.is_played && !note_ctx.is_active => (Color::Yellow, Color::Black),
+        _ if note_ctx.is_generated && note_ctx.is_active => (Color::LightMagenta, Color::Black),
+        Some(heat) if heat > 0.0 && !note_ctx.is_active => (heat_color(heat), Color::Black),
+        _ if note_ctx.in_scale && !note_ctx.is_active && is_white => {
+            (Color::LightGreen, Color::Black)
+        }
+        _ if note_ctx.in_scale && !note_ctx.is_active => (Color::Green, Color::Black),
+        _ => get_key_colors(is_white, note_ctx.is_active),
+    }
+}
 
 fn draw_white_key(
     f: &mut Frame,
@@ -174,7 +310,7 @@ fn draw_white_key(
     key_ctx: &KeyContext,
     render_ctx: &RenderContext,
 ) {
-    let (bg_color, fg_color) = get_key_colors(true, note_ctx.is_active);
+    let (bg_color, fg_color) = key_colors(true, note_ctx);
     let key_name = format!("{}{}", KEY_NAMES[key_ctx.key_index], note_ctx.octave);
 
     let key_rect = Rect {
@@ -204,7 +340,7 @@ fn draw_black_key(
     key_ctx: &KeyContext,
     render_ctx: &RenderContext,
 ) {
-    let (bg_color, fg_color) = get_key_colors(false, note_ctx.is_active);
+    let (bg_color, fg_color) = key_colors(false, note_ctx);
     let key_name = format!("{}{}", KEY_NAMES[key_ctx.key_index], note_ctx.octave);
 
     let key_rect = Rect {
@@ -0,0 +1,85 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    prelude::Color,
+    style::Style,
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Paragraph},
+};
+
+use crate::stats::session::{SessionStats, VELOCITY_BUCKETS};
+use crate::theory::key::note_name;
+
+// Keys listed as most and least played
+const RANKED_KEYS: usize = 3;
+
+fn key_list(keys: &[(u8, u32)]) -> String {
+    if keys.is_empty() {
+        return "-".to_string();
+    }
+    keys.iter()
+        .map(|(note, count)| format!("{} ({})", note_name(*note), count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub fn render(f: &mut Frame, stats: &SessionStats, area: Rect) {
+    let chunks =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).split(area);
+
+    let seconds = stats.duration / 1_000_000;
+    let ranked = stats.ranked_keys();
+    let least: Vec<(u8, u32)> = ranked.iter().rev().take(RANKED_KEYS).copied().collect();
+
+    let lines = vec![
+        Line::from(format!(
+            "Duration {}:{:02} | Notes {} | {:.0} notes/min",
+            seconds / 60,
+            seconds % 60,
+            stats.note_count,
+            stats.notes_per_minute()
+        )),
+        Line::from(format!(
+            "Distinct pitches {} | Average note {:.2}s",
+            stats.distinct_pitches(),
+            stats.average_note_length() as f64 / 1_000_000.0
+        )),
+        Line::from(format!(
+            "Sustain pedal {} presses, down {:.0}% of the time",
+            stats.pedal.presses,
+            stats.pedal_percent()
+        )),
+        Line::from(format!(
+            "Most played: {}",
+            key_list(&ranked[..ranked.len().min(RANKED_KEYS)])
+        )),
+        Line::from(format!("Least played: {}", key_list(&least))),
+    ];
+
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title(" Statistics ").borders(Borders::ALL)),
+        chunks[0],
+    );
+
+    let bucket_size = 128 / VELOCITY_BUCKETS;
+    let bars: Vec<Bar> = stats
+        .velocity_histogram
+        .iter()
+        .enumerate()
+        .map(|(bucket, count)| {
+            Bar::default()
+                .value(*count as u64)
+                .label(Line::from(format!("{}", (bucket + 1) * bucket_size - 1)))
+        })
+        .collect();
+
+    let chart = BarChart::default()
+        .block(Block::default().title(" Velocity ").borders(Borders::ALL))
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(3)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Green))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+    f.render_widget(chart, chunks[1]);
+}
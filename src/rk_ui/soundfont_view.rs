@@ -0,0 +1,158 @@
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    prelude::Color,
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use std::{collections::HashMap, error::Error, path::PathBuf, time::Duration};
+// ---
+use crate::audio::soundfont::{PresetInfo, check_header, list_presets};
+
+struct SoundfontView {
+    files: Vec<(PathBuf, Result<(), String>)>, // each checked once, when listed
+    current: Option<PathBuf>,
+    list: ListState,
+    // loaded on first view, parsing a large soundfont takes a moment
+    presets: HashMap<PathBuf, Result<Vec<PresetInfo>, String>>,
+}
+
+impl SoundfontView {
+    fn selected(&self) -> Option<&PathBuf> {
+        self.list
+            .selected()
+            .and_then(|i| self.files.get(i))
+            .map(|(path, _)| path)
+    }
+
+    fn selected_is_valid(&self) -> bool {
+        self.list
+            .selected()
+            .and_then(|i| self.files.get(i))
+            .is_some_and(|(_, header)| header.is_ok())
+    }
+
+    fn load_selected(&mut self) {
+        if let Some(path) = self.selected().cloned() {
+            self.presets
+                .entry(path.clone())
+                .or_insert_with(|| list_presets(&path));
+        }
+    }
+}
+
+fn render_files(f: &mut Frame, view: &mut SoundfontView, area: Rect) {
+    let items: Vec<ListItem> = view
+        .files
+        .iter()
+        .map(|(path, header)| {
+            let marker = if view.current.as_ref() == Some(path) {
+                "* "
+            } else {
+                "  "
+            };
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match header {
+                Ok(()) => ListItem::new(format!("{}{}", marker, name)),
+                Err(e) => ListItem::new(format!("{}{} - {}", marker, name, e))
+                    .style(Style::default().fg(Color::Red)),
+            }
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(" Soundfonts (* default) ")
+                .borders(Borders::ALL),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    f.render_stateful_widget(list, area, &mut view.list);
+}
+
+fn render_presets(f: &mut Frame, view: &SoundfontView, area: Rect) {
+    let block = Block::default().title(" Presets ").borders(Borders::ALL);
+    let lines: Vec<Line> = match view.selected().and_then(|path| view.presets.get(path)) {
+        Some(Ok(presets)) => presets
+            .iter()
+            .map(|p| Line::from(format!("{:>3}:{:<3} {}", p.bank, p.patch, p.name)))
+            .collect(),
+        Some(Err(e)) => vec![Line::from(e.as_str())],
+        None => Vec::new(),
+    };
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn ui(f: &mut Frame, view: &mut SoundfontView) {
+    let chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(f.area());
+    let columns = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[0]);
+
+    render_files(f, view, columns[0]);
+    render_presets(f, view, columns[1]);
+    f.render_widget(Paragraph::new("Enter set as default | q cancel"), chunks[1]);
+}
+
+// Pick a soundfont from the given files, None if cancelled
+pub fn run_soundfont_select(
+    files: Vec<PathBuf>,
+    current: Option<PathBuf>,
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let mut view = SoundfontView {
+        list: ListState::default().with_selected(Some(
+            current
+                .as_ref()
+                .and_then(|c| files.iter().position(|p| p == c))
+                .unwrap_or(0),
+        )),
+        files: files
+            .into_iter()
+            .map(|path| {
+                let header = check_header(&path);
+                (path, header)
+            })
+            .collect(),
+        current,
+        presets: HashMap::new(),
+    };
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let chosen = loop {
+        view.load_selected();
+        terminal.draw(|f| ui(f, &mut view))?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break None,
+                KeyCode::Down | KeyCode::Char('j') => view.list.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => view.list.select_previous(),
+                KeyCode::Enter if view.selected_is_valid() => {
+                    break view.selected().cloned();
+                }
+                _ => (),
+            }
+        }
+    };
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(chosen)
+}
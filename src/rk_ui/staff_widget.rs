@@ -0,0 +1,134 @@
+use musical_note::Key;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Widget},
+};
+// ---
+use crate::theory::key::{diatonic_index, fifths, signature_alter, spell};
+
+// Letter steps of the staff lines, see diatonic_index. Middle C sits between.
+const MIDDLE_C: i32 = 35;
+const TREBLE_LINES: [i32; 5] = [37, 39, 41, 43, 45]; // E4 G4 B4 D5 F5
+const BASS_LINES: [i32; 5] = [25, 27, 29, 31, 33]; // G2 B2 D3 F3 A3
+
+// Where the key signature's sharps and flats go on the treble staff, in order.
+// The bass staff has them two octaves lower.
+const SHARP_STEPS: [i32; 7] = [45, 42, 46, 43, 40, 44, 41];
+const FLAT_STEPS: [i32; 7] = [41, 44, 40, 43, 39, 42, 38];
+
+// A note to draw, `offset` micro seconds after the playhead
+pub struct StaffNote {
+    pub offset: i64,
+    pub note: u8,
+    pub color: Color,
+}
+
+// Notes on a treble and bass staff, spelled in the key, passing a playhead
+pub struct GrandStaff<'a> {
+    pub title: String,
+    pub notes: &'a [StaffNote],
+    pub key: Key,
+    pub span: u64,     // micro seconds across the staff
+    pub playhead: f32, // share of the width before the playhead
+}
+
+fn accidental_glyph(alter: i8) -> &'static str {
+    match alter {
+        -2 => "𝄫",
+        -1 => "♭",
+        1 => "♯",
+        2 => "𝄪",
+        _ => "♮",
+    }
+}
+
+impl Widget for GrandStaff<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default().title(self.title).borders(Borders::ALL);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.width < 8 || inner.height < 3 {
+            return;
+        }
+
+        // middle C in the middle row, one row per letter step
+        let middle = inner.y as i32 + inner.height as i32 / 2;
+        let row = |step: i32| -> Option<u16> {
+            let y = middle - (step - MIDDLE_C);
+            (inner.y as i32..inner.bottom() as i32)
+                .contains(&y)
+                .then_some(y as u16)
+        };
+        let line_style = Style::default().fg(Color::DarkGray);
+        let put = |buf: &mut Buffer, x: u16, step: i32, text: &str, style: Style| {
+            if let Some(y) = row(step)
+                && x >= inner.x
+                && x < inner.right()
+            {
+                buf.set_string(x, y, text, style);
+            }
+        };
+
+        for step in TREBLE_LINES.iter().chain(BASS_LINES.iter()) {
+            if let Some(y) = row(*step) {
+                buf.set_string(inner.x, y, "─".repeat(inner.width as usize), line_style);
+            }
+        }
+        put(buf, inner.x + 1, 39, "𝄞", Style::default());
+        put(buf, inner.x + 1, 31, "𝄢", Style::default());
+
+        // key signature after the clefs
+        let count = fifths(self.key).clamp(-7, 7);
+        let (steps, glyph) = if count >= 0 {
+            (&SHARP_STEPS, "♯")
+        } else {
+            (&FLAT_STEPS, "♭")
+        };
+        for (i, step) in steps.iter().take(count.unsigned_abs() as usize).enumerate() {
+            let x = inner.x + 3 + i as u16;
+            put(buf, x, *step, glyph, Style::default());
+            put(buf, x, step - 14, glyph, Style::default());
+        }
+
+        let left = inner.x + 4 + count.unsigned_abs() as u16;
+        let width = inner.right().saturating_sub(left + 1) as i64;
+        let playhead = left + (width as f32 * self.playhead.clamp(0.0, 1.0)) as u16;
+        for y in inner.y..inner.bottom() {
+            buf.set_string(playhead, y, "│", Style::default().fg(Color::Yellow));
+        }
+
+        let span = self.span.max(1) as i64;
+        for staff_note in self.notes {
+            let x = playhead as i64 + staff_note.offset * width / span;
+            if x < left as i64 || x >= inner.right() as i64 {
+                continue;
+            }
+            let x = x as u16;
+            let pitch = spell(staff_note.note, self.key);
+            let step = diatonic_index(pitch);
+
+            // ledger lines above, below and through middle C
+            let ledgers = (TREBLE_LINES[4] + 2..=step)
+                .chain(step..=BASS_LINES[0] - 2)
+                .chain((step == MIDDLE_C).then_some(MIDDLE_C))
+                .filter(|ledger| (ledger - MIDDLE_C) % 2 == 0);
+            for ledger in ledgers {
+                put(buf, x.saturating_sub(1), ledger, "───", line_style);
+            }
+
+            if pitch.alter != signature_alter(self.key, pitch.step) {
+                let style = Style::default().fg(staff_note.color);
+                put(
+                    buf,
+                    x.saturating_sub(1),
+                    step,
+                    accidental_glyph(pitch.alter),
+                    style,
+                );
+            }
+            put(buf, x, step, "●", Style::default().fg(staff_note.color));
+        }
+    }
+}
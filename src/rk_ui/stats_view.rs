@@ -0,0 +1,73 @@
+use crossterm::{
+    event::{self, Event, KeyCode},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::Paragraph,
+};
+use std::{error::Error, time::Duration};
+// ---
+use crate::rk_ui::{render_piano, render_stats, types::UiEngine};
+use crate::stats::session::SessionStats;
+use crate::types::recording::Recording;
+
+fn ui(f: &mut Frame, engine: &mut UiEngine) {
+    let chunks = Layout::vertical([
+        Constraint::Length(1),      // Title
+        Constraint::Percentage(60), // Statistics
+        Constraint::Fill(1),        // Heatmap keyboard
+    ])
+    .split(f.area());
+
+    f.render_widget(
+        Paragraph::new(Line::from(format!(
+            "{} - press q to close",
+            engine.recording.title
+        ))),
+        chunks[0],
+    );
+    if let Some(stats) = &engine.stats {
+        render_stats::render(f, stats, chunks[1]);
+    }
+    let (low, high) = engine.key_range;
+    render_piano::render(f, engine, chunks[2], low, high);
+}
+
+// Statistics of a saved recording, with the keyboard as a heatmap
+pub fn run_stats(recording: Recording) -> Result<(), Box<dyn Error>> {
+    let mut engine = UiEngine::new(&recording.title);
+    engine.stats = Some(SessionStats::from_messages(&recording.messages));
+    // show every key the recording uses
+    for (_, [status, note, velocity]) in &recording.messages {
+        if status & 0xf0 == 0x90 && *velocity > 0 {
+            engine.fit_key(*note);
+        }
+    }
+    engine.show_stats = true;
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        terminal.draw(|f| ui(f, &mut engine))?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            break;
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(())
+}
@@ -1,28 +1,153 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Instant};
 
 use midir::MidiInputConnection;
+use musical_note::Key;
 use ratatui::{layout::Rect, style::Color};
+use serde::{Deserialize, Serialize};
 
+use crate::audio::device::OutputConfig;
+use crate::audio::effects::{EffectSettings, EffectsHandle};
+use crate::audio::live_synth::LiveSynthHandle;
+use crate::pipeline::{harmony::GeneratedNotes, transpose::TransposeHandle, zones::Zone};
+use crate::practice::{piece::Hand, play_along::PlayAlong};
+use crate::stats::session::SessionStats;
+use crate::theory::key::note_name;
 use crate::types::midi::{Message, MessageData};
+use crate::types::recording::Recording;
 
-// todo
-enum PianoKeyCount {
+// Common keyboard sizes, by number of keys
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PianoKeyCount {
     A = 88,
     B = 76,
     C = 61,
     D = 49,
 }
+
+impl PianoKeyCount {
+    pub const ALL: [PianoKeyCount; 4] = [
+        PianoKeyCount::A,
+        PianoKeyCount::B,
+        PianoKeyCount::C,
+        PianoKeyCount::D,
+    ];
+
+    // Lowest and highest key, A0 - C8 on a full piano
+    pub fn range(self) -> (u8, u8) {
+        match self {
+            PianoKeyCount::A => (21, 108),
+            PianoKeyCount::B => (28, 103),
+            PianoKeyCount::C => (36, 96),
+            PianoKeyCount::D => (36, 84),
+        }
+    }
+}
+
+// The keys drawn, by keyboard size or the lowest and highest note
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum KeyRange {
+    Size(PianoKeyCount),
+    Notes(u8, u8),
+}
+
+impl KeyRange {
+    pub fn notes(self) -> (u8, u8) {
+        match self {
+            KeyRange::Size(count) => count.range(),
+            KeyRange::Notes(low, high) => (low.min(high), high.max(low).min(127)),
+        }
+    }
+
+    pub fn label(self) -> String {
+        let (low, high) = self.notes();
+        match self {
+            KeyRange::Size(count) => format!("{} keys", count as u8),
+            KeyRange::Notes(..) => format!("{} - {}", note_name(low), note_name(high)),
+        }
+    }
+}
+
+// Which keys of the piano are marked as in the scale
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ScaleHighlight {
+    #[default]
+    Off,
+    Detected, // the key of what was just played
+    Chosen,   // the scale chosen to practise, see Prefs::practice_scale
+}
+
+impl ScaleHighlight {
+    pub fn label(self) -> &'static str {
+        match self {
+            ScaleHighlight::Off => "off",
+            ScaleHighlight::Detected => "detected key",
+            ScaleHighlight::Chosen => "chosen scale",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyboardSettings {
+    pub range: KeyRange,
+    pub expand: bool, // widen the keyboard for notes played outside it
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        KeyboardSettings {
+            range: KeyRange::Size(PianoKeyCount::A),
+            expand: true,
+        }
+    }
+}
+
 pub struct UiEngine {
     pub falling_notes: Vec<NoteBar>,
     pub piano_keys: Vec<bool>, // Simple array for which keys are pressed
     pub should_quit: bool,
+    pub play_along: Option<PlayAlong>,
+    pub recording: Recording,
+    pub show_stats: bool,
+    pub stats: Option<SessionStats>,
+    pub synth: Option<LiveSynthHandle>,
+    pub output: Option<OutputConfig>, // what the synth's output agreed with the device
+    pub effects: Option<EffectsHandle>,
+    pub show_effects: bool,
+    pub effect_param: usize, // index into Param::ALL
+    pub effect_presets: Vec<(String, EffectSettings)>, // loaded when the panel opens
+    pub effect_preset: Option<usize>,
+    pub transpose: Option<TransposeHandle>,
+    pub played_keys: Vec<Option<u8>>, // the key pressed for each sounding note
+    pub show_played_keys: bool,
+    pub zones: Vec<Zone>, // drawn under the piano while the synth plays through them
+    pub tuning: Option<String>, // shown when the synth is not in 12-TET
+    pub arp: Option<String>,    // the arpeggiator's settings, while it plays
+    pub generated: Option<GeneratedNotes>, // set while the harmoniser adds notes
+    pub generated_keys: Vec<bool>, // sounding keys the harmoniser started
+    pub key_range: (u8, u8),       // lowest and highest key drawn
+    pub expand_keys: bool,
+    pub clock_origin: Option<Instant>, // when the MIDI timestamps count from
+    pub fall_window: u64,              // micro seconds of notes on screen
+    pub show_upcoming: bool,           // a piece's notes ahead instead of those played
+    pub expected_keys: Vec<bool>,      // keys the piece wants held now
+    pub show_staff: bool,              // a grand staff instead of falling notes
+    pub staff_key: Option<Key>,        // chosen, otherwise detected from what is played
+    pub detected_key: Option<(usize, Key)>, // and the message count it was found from
+    pub scale_highlight: ScaleHighlight,
+    pub practice_scale: Option<Key>,
+    pub recent_key: Option<(Key, f32)>, // of the last KEY_WINDOW_MS, and how well it fits
+    pub recent_key_at: Option<(usize, u64)>, // message count and clock time it was found at
+    pub scale_classes: [bool; 12],      // pitch classes highlighted on the piano
+    pub key_window: u64,
 }
 
 pub struct NoteBar {
     pub note: u8,     // MessageData[1]
     pub velocity: u8, // MessageData[2]
-    pub y_position: f32, // Current position (calculated from elapsed time)
-                      // pub spawn_time: u64, // Original timestamp from Message
+    pub hand: Option<Hand>, // set while playing along
+    pub start: u64,         // note on, micro seconds on the engine clock
+    pub end: Option<u64>,   // note off, None while the key is held
                       // pub is_hit: bool,
                       // pub timing_feedback: Option<usize>, // ?
 }
@@ -50,6 +175,11 @@ pub struct PianoKey {
 pub struct NoteContext {
     pub octave: i32,
     pub is_active: bool,
+    pub is_played: bool,    // pressed on the keyboard, shown while transposing
+    pub is_generated: bool, // added by the harmoniser
+    pub is_expected: bool,  // to be held now while playing along
+    pub in_scale: bool,     // marked while highlighting a scale
+    pub heat: Option<f32>,  // share of the most played key's count, when showing stats
 }
 
 pub struct KeyContext {
@@ -1,10 +1,19 @@
 use crate::{
+    practice::{
+        piece::Hand,
+        play_along::{OtherHand, PlayAlong, PracticeHands},
+        scoring::Judgement,
+    },
     rk_ui::{
         constants::PIANO_PATTERN,
+        render_effects,
         render_piano::{self},
+        render_stats,
+        staff_widget::{GrandStaff, StaffNote},
         types::{NoteBar, UiEngine},
         util::count_white_keys_in_range,
     },
+    theory::key::{key_name, parse_key},
     types::midi::Message,
 };
 use crossterm::{
@@ -15,14 +24,18 @@ use crossterm::{
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Rect},
     prelude::Color,
     style::Style,
-    widgets::{Block, Borders},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
 };
-use std::{process::exit, sync::mpsc::Receiver};
+use std::sync::mpsc::Receiver;
 
-pub fn run_app(midi_receiver: Receiver<Vec<Message>>) -> Result<(), Box<dyn std::error::Error>> {
+pub fn run_app(
+    midi_receiver: Receiver<Vec<Message>>,
+    mut engine: UiEngine,
+) -> Result<UiEngine, Box<dyn std::error::Error>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -30,16 +43,19 @@ pub fn run_app(midi_receiver: Receiver<Vec<Message>>) -> Result<(), Box<dyn std:
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut engine = UiEngine::new();
-
     loop {
         // Process MIDI messages (non-blocking)
         while let Ok(message) = midi_receiver.try_recv() {
             process_midi_message(&mut engine, message);
         }
 
+        // Mark notes that were never played
+        engine.tick_play_along();
+
         // Update falling notes positions
         update_falling_notes(&mut engine);
+        engine.refresh_stats();
+        engine.update_scale();
 
         // Render UI
         terminal.draw(|f| ui(f, &mut engine))?;
@@ -51,6 +67,27 @@ pub fn run_app(midi_receiver: Receiver<Vec<Message>>) -> Result<(), Box<dyn std:
                     KeyCode::Char('q') | KeyCode::Esc => {
                         engine.should_quit = true;
                     }
+                    KeyCode::Char('s') => engine.toggle_stats(),
+                    KeyCode::Char('e') => engine.toggle_effects(),
+                    KeyCode::Up if engine.show_effects => engine.select_effect_param(-1),
+                    KeyCode::Down if engine.show_effects => engine.select_effect_param(1),
+                    KeyCode::Left if engine.show_effects => engine.adjust_effect_param(-1),
+                    KeyCode::Right if engine.show_effects => engine.adjust_effect_param(1),
+                    KeyCode::Char(' ') if engine.show_effects => engine.toggle_effect(),
+                    KeyCode::Char('[') if engine.show_effects => engine.cycle_effect_preset(-1),
+                    KeyCode::Char(']') if engine.show_effects => engine.cycle_effect_preset(1),
+                    KeyCode::Char('-') => engine.shift_transpose(-1, 0),
+                    KeyCode::Char('+') | KeyCode::Char('=') => engine.shift_transpose(1, 0),
+                    KeyCode::Char(',') => engine.shift_transpose(0, -1),
+                    KeyCode::Char('.') => engine.shift_transpose(0, 1),
+                    KeyCode::Char('0') => engine.reset_transpose(),
+                    KeyCode::Char('k') => engine.toggle_played_keys(),
+                    KeyCode::Char('u') => engine.toggle_upcoming(),
+                    KeyCode::Char('n') => engine.toggle_staff(),
+                    KeyCode::Char('/') => engine.cycle_staff_key(),
+                    KeyCode::Char('h') => engine.cycle_scale_highlight(),
+                    KeyCode::Up => engine.zoom_fall_window(-20),
+                    KeyCode::Down => engine.zoom_fall_window(25),
                     _ => (),
                 }
             }
@@ -66,79 +103,175 @@ pub fn run_app(midi_receiver: Receiver<Vec<Message>>) -> Result<(), Box<dyn std:
     // Cleanup
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    Ok(())
+    Ok(engine)
 }
 
 fn process_midi_message(engine: &mut UiEngine, messages: Vec<Message>) {
-    for (_timestamp, [status, note, velocity]) in messages {
+    for (timestamp, [status, note, velocity]) in messages {
+        engine.recording.push((timestamp, [status, note, velocity]));
         match status {
             0x90..=0x9f if velocity > 0 => {
                 // 144..159 midi NOTE_ON for channel_x
-                engine.add_note(note, velocity);
+                // judged on the key pressed, the piece is read untransposed.
+                // Harmony is shown but never judged.
+                if !engine.press_generated_key(note) {
+                    let key = engine.press_played_key(note);
+                    engine.judge_note(timestamp, key);
+                }
+                engine.add_note(timestamp, note, velocity);
                 engine.try_press_key(note);
             }
             0x80..=0x8f | 0x90..=0x9f if velocity == 0 => {
                 // 128..143 NOTE_OFF | 144..159 midi NOTE_ON but vel = 0 for channel_x
                 engine.try_release_key(note);
+                engine.release_played_key(note);
+                engine.end_note(timestamp, note);
             }
             _ => {
-                eprintln!("Unhandled midi status");
-                exit(1);
+                // pedals, program changes and the rest are only recorded
             }
         }
     }
 }
 
 fn update_falling_notes(engine: &mut UiEngine) {
-    let fall_speed = 0.02; // Adjust this to control speed (higher = faster)
-    engine.update_pos(fall_speed);
+    engine.update_pos();
 }
 
 fn ui(f: &mut Frame, engine: &mut UiEngine) {
+    // keep the detected key up to date with what has been played
+    if engine.show_staff {
+        engine.staff_key();
+    }
+    let status_height = if engine.play_along.is_some() { 3 } else { 0 };
     let chunks = Layout::vertical([
-        Constraint::Percentage(75), // Falling notes area
-        Constraint::Percentage(25), // Piano keyboard area
+        Constraint::Length(status_height), // Play-along status
+        Constraint::Percentage(75),        // Falling notes area
+        Constraint::Percentage(25),        // Piano keyboard area
     ])
     .split(f.area());
 
-    render_falling_notes(f, engine, chunks[0]);
-    render_piano::render(f, engine, chunks[1], 21, 108);
+    if let Some(play_along) = &engine.play_along {
+        render_play_along_status(f, play_along, chunks[0]);
+    }
+    match (&engine.effects, &engine.stats) {
+        (Some(effects), _) if engine.show_effects => {
+            let preset = engine
+                .effect_preset
+                .map(|i| engine.effect_presets[i].0.as_str());
+            render_effects::render(f, &effects.get(), engine.effect_param, preset, chunks[1]);
+        }
+        (_, Some(stats)) if engine.show_stats => render_stats::render(f, stats, chunks[1]),
+        _ if engine.show_staff => render_staff(f, engine, chunks[1]),
+        _ => match &engine.play_along {
+            Some(play_along) if engine.show_upcoming => {
+                render_upcoming_notes(f, engine, play_along, chunks[1])
+            }
+            _ => render_falling_notes(f, engine, chunks[1]),
+        },
+    }
+    let (low, high) = engine.key_range;
+    render_piano::render(f, engine, chunks[2], low, high);
+}
+
+fn render_play_along_status(f: &mut Frame, play_along: &PlayAlong, area: Rect) {
+    let scorer = &play_along.scorer;
+    let block = Block::default()
+        .title(format!(
+            " {} @ {}% - {} ",
+            play_along.piece.name,
+            play_along.tempo_percent,
+            match (play_along.hands, play_along.other_hand) {
+                (PracticeHands::Both, _) => "both hands",
+                (PracticeHands::Only(Hand::Left), OtherHand::Mute) => "left hand",
+                (PracticeHands::Only(Hand::Left), OtherHand::AutoPlay) => "left hand, right auto",
+                (PracticeHands::Only(Hand::Right), OtherHand::Mute) => "right hand",
+                (PracticeHands::Only(Hand::Right), OtherHand::AutoPlay) => "right hand, left auto",
+            }
+        ))
+        .borders(Borders::ALL);
+
+    let progress = match play_along.count_in() {
+        Some(seconds) => format!("Starting in {}", seconds),
+        None if scorer.is_finished() => "Finished - press q to save".to_string(),
+        None => format!("{}/{}", scorer.judged_count(), scorer.expected_count()),
+    };
+
+    let last_color = match scorer.last {
+        Some(Judgement::Perfect) => Color::Green,
+        Some(Judgement::Good) => Color::LightGreen,
+        Some(Judgement::Early) | Some(Judgement::Late) => Color::Yellow,
+        Some(Judgement::Missed) | Some(Judgement::WrongNote) => Color::Red,
+        None => Color::Reset,
+    };
+
+    let line = Line::from(vec![
+        Span::raw(format!(
+            "Accuracy {:.1}% | Streak {} (best {}) | ",
+            scorer.accuracy(),
+            scorer.streak,
+            scorer.best_streak
+        )),
+        Span::styled(
+            scorer.last.map(|j| j.label()).unwrap_or("-"),
+            Style::default().fg(last_color),
+        ),
+        Span::raw(format!(" | {}", progress)),
+    ]);
+
+    f.render_widget(Paragraph::new(line).block(block), area);
 }
 
 fn render_falling_notes(f: &mut Frame, engine: &UiEngine, area: ratatui::layout::Rect) {
     let block = Block::default()
-        .title(" Falling Notes ")
+        .title(format!(
+            " Falling Notes - {:.1} s ",
+            engine.fall_window as f64 / 1_000_000.0
+        ))
         .borders(Borders::ALL);
     let inner_area = block.inner(area);
     f.render_widget(block, area);
 
+    let now = engine.clock_now();
+    let height = inner_area.height as u64;
+
     // Convert notes to visual positions
     for &NoteBar {
         note,
-        y_position,
         velocity,
+        hand,
+        start,
+        end,
     } in &engine.falling_notes
     {
-        let x_pos = map_note_to_x_position(note, inner_area.width);
+        if !engine.key_in_range(note) {
+            continue;
+        }
+        let x_pos = map_note_to_x_position(note, inner_area.width, engine.key_range);
 
-        // Convert y_position (0.0-1.0) to actual screen coordinates
-        let y_pos = (y_position * inner_area.height as f32) as u16;
+        // Notes enter at the top as they start and fall a full height per window,
+        // so a bar is as long as the key was held
+        let row = |time: u64| now.saturating_sub(time) * height / engine.fall_window.max(1);
+        let top = end.map_or(0, row);
+        let bottom = row(start).min(height.saturating_sub(1));
 
-        if y_pos < inner_area.height {
-            let color = match velocity {
-                0..=42 => Color::Blue,
-                43..=84 => Color::Green,
-                85..=127 => Color::White,
-                _ => Color::Black,
+        if top < height {
+            let color = match (hand, velocity) {
+                (Some(Hand::Left), _) => Color::Magenta,
+                (Some(Hand::Right), _) => Color::Cyan,
+                (None, 0..=42) => Color::Blue,
+                (None, 43..=84) => Color::Green,
+                (None, 85..=127) => Color::White,
+                (None, _) => Color::Black,
             };
 
             let note_widget = Block::default().style(Style::default().bg(color));
 
             let note_area = ratatui::layout::Rect {
                 x: inner_area.x + x_pos,
-                y: inner_area.y + y_pos,
-                width: 2,
-                height: 1,
+                y: inner_area.y + top as u16,
+                width: 2.min(inner_area.width.saturating_sub(x_pos)),
+                height: (bottom.max(top) - top + 1) as u16,
             };
 
             f.render_widget(note_widget, note_area);
@@ -146,11 +279,115 @@ fn render_falling_notes(f: &mut Frame, engine: &UiEngine, area: ratatui::layout:
     }
 }
 
-// Map MIDI notes to screen width
-fn map_note_to_x_position(midi_note: u8, screen_width: u16) -> u16 {
-    let min_note = 21; // e.g 60 is Middle C
-    let max_note = 108; // 84 is C, two octaves up
+// A piece's notes coming down to the keys, reaching them when they are due. The
+// bottom edge of a bar is its start, so its length is the note's duration.
+fn render_upcoming_notes(f: &mut Frame, engine: &UiEngine, play_along: &PlayAlong, area: Rect) {
+    let block = Block::default()
+        .title(format!(
+            " Upcoming Notes - {:.1} s ",
+            engine.fall_window as f64 / 1_000_000.0
+        ))
+        .borders(Borders::ALL);
+    let inner_area = block.inner(area);
+    f.render_widget(block, area);
+
+    let now = play_along.now();
+    let height = inner_area.height as i64;
+    let window = engine.fall_window.max(1) as i64;
+    // rows above the keys, 0 is the row just over them
+    let rows_ahead = |time: i64| (time - now).max(0) * height / window;
+
+    for upcoming in play_along.upcoming(engine.fall_window) {
+        if !engine.key_in_range(upcoming.note) {
+            continue;
+        }
+        let low = rows_ahead(upcoming.time);
+        let high = rows_ahead(upcoming.end).min(height - 1);
+        if low >= height {
+            continue;
+        }
+
+        let color = match (upcoming.practised, upcoming.hand) {
+            (false, _) => Color::DarkGray,
+            (true, Hand::Left) => Color::Magenta,
+            (true, Hand::Right) => Color::Cyan,
+        };
+        let x_pos = map_note_to_x_position(upcoming.note, inner_area.width, engine.key_range);
+        let note_area = Rect {
+            x: inner_area.x + x_pos,
+            y: inner_area.y + (height - 1 - high) as u16,
+            width: 2.min(inner_area.width.saturating_sub(x_pos)),
+            height: (high.max(low) - low + 1) as u16,
+        };
+        f.render_widget(Block::default().style(Style::default().bg(color)), note_area);
+    }
+}
+
+// What was just played, and the piece's notes ahead when playing along
+fn render_staff(f: &mut Frame, engine: &UiEngine, area: Rect) {
+    let key = engine
+        .staff_key
+        .or(engine.detected_key.map(|(_, key)| key))
+        .or_else(|| parse_key("C"))
+        .expect("C is a key");
+    let span = engine.fall_window;
+
+    let now = engine.clock_now();
+    let mut notes: Vec<StaffNote> = engine
+        .recording
+        .messages
+        .iter()
+        .rev()
+        .take_while(|(time, _)| now.saturating_sub(*time) < span)
+        .filter(|(_, [status, _, velocity])| status & 0xf0 == 0x90 && *velocity > 0)
+        .map(|(time, [_, note, _])| StaffNote {
+            offset: *time as i64 - now as i64,
+            note: *note,
+            color: Color::White,
+        })
+        .collect();
+
+    let playhead = match &engine.play_along {
+        Some(play_along) => {
+            let now = play_along.now();
+            notes.extend(play_along.upcoming(span).into_iter().map(|upcoming| StaffNote {
+                offset: upcoming.time - now,
+                note: upcoming.note,
+                color: match (upcoming.practised, upcoming.hand) {
+                    (false, _) => Color::DarkGray,
+                    (true, Hand::Left) => Color::Magenta,
+                    (true, Hand::Right) => Color::Cyan,
+                },
+            }));
+            0.25
+        }
+        None => 0.9,
+    };
+
+    let title = format!(
+        " Grand Staff - {}{} - {:.1} s ",
+        key_name(key),
+        if engine.staff_key.is_some() { "" } else { " (detected)" },
+        span as f64 / 1_000_000.0
+    );
+    f.render_widget(
+        GrandStaff {
+            title,
+            notes: &notes,
+            key,
+            span,
+            playhead,
+        },
+        area,
+    );
+}
 
+// Map MIDI notes to screen width, over the keys the piano shows
+fn map_note_to_x_position(
+    midi_note: u8,
+    screen_width: u16,
+    (min_note, max_note): (u8, u8),
+) -> u16 {
     if midi_note < min_note {
         return 0;
     }
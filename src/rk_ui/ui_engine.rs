@@ -1,24 +1,162 @@
-use crate::rk_ui::types::{NoteBar, UiEngine};
+use std::{env, time::Instant};
+
+use musical_note::Key;
+
+use crate::audio::effects::Param;
+use crate::rk_ui::constants::PIANO_PATTERN;
+use crate::rk_ui::types::{NoteBar, ScaleHighlight, UiEngine};
+use crate::stats::session::SessionStats;
+use crate::theory::{
+    key::{parse_key, scale_pitch_classes},
+    key_finding::{detect_key, detect_recent_key},
+};
+use crate::types::recording::Recording;
+use crate::util::prefs::Prefs;
+
+// Keys the staff can be set to, by fifths
+const STAFF_KEYS: [&str; 12] = [
+    "C", "G", "D", "A", "E", "B", "F#", "Db", "Ab", "Eb", "Bb", "F",
+];
+
+// Seconds of notes the falling notes area shows, unless FALL_WINDOW_MS is set
+const DEFAULT_FALL_WINDOW_MS: u64 = 4000;
+const MIN_FALL_WINDOW_MS: u64 = 500;
+const MAX_FALL_WINDOW_MS: u64 = 30_000;
+
+fn fall_window() -> u64 {
+    env::var("FALL_WINDOW_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_FALL_WINDOW_MS)
+        .clamp(MIN_FALL_WINDOW_MS, MAX_FALL_WINDOW_MS)
+        * 1000
+}
+
+// Seconds of playing the live key is found from, unless KEY_WINDOW_MS is set
+const DEFAULT_KEY_WINDOW_MS: u64 = 15_000;
+// The window moves on while nothing is played, so the key is found again this often
+const KEY_RECHECK: u64 = 1_000_000;
+
+fn key_window() -> u64 {
+    env::var("KEY_WINDOW_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_KEY_WINDOW_MS)
+        .max(1000)
+        * 1000
+}
 
 impl UiEngine {
     // --- INIT ---
-    pub fn new() -> Self {
+    pub fn new(title: &str) -> Self {
+        let prefs = Prefs::load();
+        let keyboard = prefs.keyboard;
         Self {
             falling_notes: Vec::new(),
             piano_keys: vec![false; 128],
             should_quit: false,
+            play_along: None,
+            recording: Recording::new(title),
+            show_stats: false,
+            stats: None,
+            synth: None,
+            output: None,
+            effects: None,
+            show_effects: false,
+            effect_param: 0,
+            effect_presets: Vec::new(),
+            effect_preset: None,
+            transpose: None,
+            played_keys: vec![None; 128],
+            show_played_keys: false,
+            zones: Vec::new(),
+            tuning: None,
+            arp: None,
+            generated: None,
+            generated_keys: vec![false; 128],
+            key_range: keyboard.range.notes(),
+            expand_keys: keyboard.expand,
+            clock_origin: None,
+            fall_window: fall_window(),
+            show_upcoming: true,
+            expected_keys: vec![false; 128],
+            show_staff: false,
+            staff_key: None,
+            detected_key: None,
+            scale_highlight: prefs.scale_highlight,
+            practice_scale: prefs.practice_scale,
+            recent_key: None,
+            recent_key_at: None,
+            scale_classes: [false; 12],
+            key_window: key_window(),
         }
     }
 		
     // --- API ---
-    pub fn add_note(&mut self, note: u8, velocity: u8) {
+    pub fn add_note(&mut self, timestamp: u64, note: u8, velocity: u8) {
+        self.fit_key(note);
+        // a key struck again without a release ends its last bar
+        self.end_note(timestamp, note);
+        let hand = self.play_along.as_ref().map(|p| p.piece.hand_for(note));
         self.falling_notes.push(NoteBar {
             note,
-            y_position: 0.0,
             velocity,
+            hand,
+            start: timestamp,
+            end: None,
         });
     }
 
+    // Close the bar of a released key
+    pub fn end_note(&mut self, timestamp: u64, note: u8) {
+        if let Some(bar) = self
+            .falling_notes
+            .iter_mut()
+            .rev()
+            .find(|bar| bar.note == note && bar.end.is_none())
+        {
+            bar.end = Some(timestamp.max(bar.start));
+        }
+    }
+
+    // Start the engine clock. midir timestamps count from when the port was
+    // opened, so this is called straight after opening the connection.
+    pub fn start_clock(&mut self) {
+        self.clock_origin = Some(Instant::now());
+    }
+
+    // Micro seconds on the same clock as the MIDI timestamps
+    pub fn clock_now(&self) -> u64 {
+        self.clock_origin
+            .map(|origin| origin.elapsed().as_micros() as u64)
+            .unwrap_or(0)
+    }
+
+    pub fn zoom_fall_window(&mut self, percent: i64) {
+        let window = self.fall_window as i64 * (100 + percent) / 100;
+        self.fall_window =
+            (window as u64).clamp(MIN_FALL_WINDOW_MS * 1000, MAX_FALL_WINDOW_MS * 1000);
+    }
+
+    // Widen the keyboard to take in a note played outside it, ending on a white key
+    pub fn fit_key(&mut self, note: u8) {
+        let note = note.min(127);
+        let (low, high) = &mut self.key_range;
+        if !self.expand_keys || (*low..=*high).contains(&note) {
+            return;
+        }
+        let is_white = PIANO_PATTERN[(note % 12) as usize];
+        if note < *low {
+            *low = if is_white { note } else { note - 1 };
+        } else {
+            *high = if is_white { note } else { note + 1 };
+        }
+    }
+
+    pub fn key_in_range(&self, note: u8) -> bool {
+        (self.key_range.0..=self.key_range.1).contains(&note)
+    }
+
     pub fn note_active(&mut self, note: u8) -> &bool {
         return self.piano_keys.get(note as usize).unwrap_or(&false);
     }
@@ -35,11 +173,252 @@ impl UiEngine {
         }
     }
 
-    pub fn update_pos(&mut self, fall_speed: f32) {
-        self.falling_notes
-            .iter_mut()
-            .for_each(|note| note.y_position += fall_speed);
+    // Remember which key was pressed for a sounding note, by undoing the transpose
+    pub fn press_played_key(&mut self, note: u8) -> u8 {
+        let offset = self.transpose.as_ref().map(|t| t.get().offset()).unwrap_or(0);
+        let key = (note as i32 - offset).clamp(0, 127) as u8;
+        if let Some(played) = self.played_keys.get_mut(note as usize) {
+            *played = Some(key);
+        }
+        key
+    }
+
+    pub fn release_played_key(&mut self, note: u8) {
+        if let Some(played) = self.played_keys.get_mut(note as usize) {
+            *played = None;
+        }
+    }
+
+    // Whether a note starting now came from the harmoniser rather than a key
+    pub fn press_generated_key(&mut self, note: u8) -> bool {
+        let generated = self
+            .generated
+            .as_ref()
+            .is_some_and(|g| g.is_generated(note));
+        if let Some(key) = self.generated_keys.get_mut(note as usize) {
+            *key = generated;
+        }
+        generated
+    }
+
+    pub fn key_generated(&self, note: u8) -> bool {
+        self.generated_keys
+            .get(note as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    // Find the key of the recent playing and mark the scale to highlight
+    pub fn update_scale(&mut self) {
+        if self.scale_highlight == ScaleHighlight::Detected {
+            let (count, now) = (self.recording.messages.len(), self.clock_now());
+            if self.recent_key_at.is_none_or(|(found_count, found_at)| {
+                found_count != count || now >= found_at + KEY_RECHECK
+            }) {
+                self.recent_key =
+                    detect_recent_key(&self.recording.messages, now, self.key_window);
+                self.recent_key_at = Some((count, now));
+            }
+        } else {
+            self.recent_key = None;
+            self.recent_key_at = None;
+        }
+        let key = match self.scale_highlight {
+            ScaleHighlight::Off => None,
+            ScaleHighlight::Detected => self.recent_key.map(|(key, _)| key),
+            ScaleHighlight::Chosen => self.practice_scale,
+        };
+        self.scale_classes = [false; 12];
+        if let Some(key) = key {
+            for class in scale_pitch_classes(key) {
+                self.scale_classes[class as usize % 12] = true;
+            }
+        }
+    }
+
+    // Off, the detected key, then the chosen scale when there is one
+    pub fn cycle_scale_highlight(&mut self) {
+        self.scale_highlight = match self.scale_highlight {
+            ScaleHighlight::Off => ScaleHighlight::Detected,
+            ScaleHighlight::Detected if self.practice_scale.is_some() => ScaleHighlight::Chosen,
+            _ => ScaleHighlight::Off,
+        };
+    }
+
+    pub fn key_in_scale(&self, note: u8) -> bool {
+        self.scale_classes[(note % 12) as usize]
+    }
+
+    pub fn key_expected(&self, note: u8) -> bool {
+        self.expected_keys
+            .get(note as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    pub fn toggle_staff(&mut self) {
+        self.show_staff = !self.show_staff;
+    }
+
+    // Step the staff's key round the circle of fifths, back to detecting it after F
+    pub fn cycle_staff_key(&mut self) {
+        let index = self
+            .staff_key
+            .and_then(|key| STAFF_KEYS.iter().position(|name| parse_key(name) == Some(key)));
+        self.staff_key = match index {
+            None => parse_key(STAFF_KEYS[0]),
+            Some(i) if i + 1 < STAFF_KEYS.len() => parse_key(STAFF_KEYS[i + 1]),
+            Some(_) => None,
+        };
+    }
+
+    // The chosen key, or the one the recording sounds like, C major until it has notes
+    pub fn staff_key(&mut self) -> Key {
+        if let Some(key) = self.staff_key {
+            return key;
+        }
+        let count = self.recording.messages.len();
+        if self.detected_key.is_none_or(|(detected_at, _)| detected_at != count)
+            && let Some(key) = detect_key(&self.recording.messages)
+        {
+            self.detected_key = Some((count, key));
+        }
+        self.detected_key
+            .map(|(_, key)| key)
+            .or_else(|| parse_key(STAFF_KEYS[0]))
+            .expect("C is a key")
+    }
+
+    // Between a piece's notes ahead and the notes played, while playing along
+    pub fn toggle_upcoming(&mut self) {
+        self.show_upcoming = !self.show_upcoming;
+    }
+
+    pub fn key_played(&self, key: u8) -> bool {
+        self.show_played_keys && self.played_keys.contains(&Some(key))
+    }
+
+    pub fn shift_transpose(&mut self, semitones: i8, octaves: i8) {
+        if let Some(transpose) = &self.transpose {
+            transpose.modify(|t| {
+                t.shift_semitones(semitones);
+                t.shift_octaves(octaves);
+            });
+        }
+    }
+
+    pub fn reset_transpose(&mut self) {
+        if let Some(transpose) = &self.transpose {
+            transpose.modify(|t| *t = Default::default());
+        }
+    }
+
+    pub fn toggle_played_keys(&mut self) {
+        self.show_played_keys = !self.show_played_keys;
+    }
+
+    pub fn judge_note(&mut self, timestamp: u64, note: u8) {
+        if let Some(play_along) = self.play_along.as_mut() {
+            play_along.note_on(timestamp, note);
+        }
+    }
+
+    pub fn tick_play_along(&mut self) {
+        let now = self.clock_now();
+        if let Some(play_along) = self.play_along.as_mut() {
+            play_along.tick();
+
+            self.expected_keys.fill(false);
+            for note in play_along.expected_keys() {
+                self.expected_keys[(note & 0x7f) as usize] = true;
+            }
+
+            // Show the auto-played hand alongside what is being played
+            for auto_note in play_along.due_auto_notes() {
+                self.falling_notes.push(NoteBar {
+                    note: auto_note.note,
+                    velocity: auto_note.velocity,
+                    hand: Some(auto_note.hand),
+                    start: now,
+                    end: Some(now + auto_note.duration),
+                });
+            }
+        }
+    }
+
+    // The effects panel needs a live synth to control
+    pub fn toggle_effects(&mut self) {
+        if self.effects.is_some() {
+            self.show_effects = !self.show_effects;
+        }
+        if self.show_effects {
+            self.effect_presets = Prefs::load().effect_presets.into_iter().collect();
+        }
+    }
+
+    pub fn select_effect_param(&mut self, offset: i32) {
+        let count = Param::ALL.len() as i32;
+        self.effect_param = (self.effect_param as i32 + offset).rem_euclid(count) as usize;
+    }
+
+    pub fn adjust_effect_param(&mut self, steps: i32) {
+        let param = Param::ALL[self.effect_param];
+        if let Some(effects) = &self.effects {
+            effects.modify(|settings| settings.adjust(param, steps));
+            self.effect_preset = None;
+        }
+    }
+
+    // Switch the selected parameter's effect on or off
+    pub fn toggle_effect(&mut self) {
+        let effect = Param::ALL[self.effect_param].effect();
+        if let Some(effects) = &self.effects {
+            effects.modify(|settings| settings.toggle(effect));
+            self.effect_preset = None;
+        }
+    }
+
+    pub fn cycle_effect_preset(&mut self, offset: i32) {
+        let count = self.effect_presets.len() as i32;
+        let Some(effects) = &self.effects else {
+            return;
+        };
+        if count == 0 {
+            return;
+        }
+        let index = match self.effect_preset {
+            Some(index) => (index as i32 + offset).rem_euclid(count),
+            None if offset < 0 => count - 1,
+            None => 0,
+        } as usize;
+        let (_, preset) = &self.effect_presets[index];
+        effects.modify(|settings| *settings = preset.clone());
+        self.effect_preset = Some(index);
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+    }
+
+    // Recount the session so far, only while the statistics are shown
+    pub fn refresh_stats(&mut self) {
+        if self.show_stats {
+            self.stats = Some(SessionStats::from_messages(&self.recording.messages));
+        }
+    }
+
+    pub fn key_heat(&self, note: u8) -> Option<f32> {
+        match (&self.stats, self.show_stats) {
+            (Some(stats), true) => Some(stats.heat(note)),
+            _ => None,
+        }
+    }
+
+    pub fn update_pos(&mut self) {
         // Remove notes that have fallen off the bottom
-        self.falling_notes.retain(|note| note.y_position < 1.0);
+        let now = self.clock_now();
+        let window = self.fall_window;
+        self.falling_notes
+            .retain(|note| note.end.is_none_or(|end| now.saturating_sub(end) < window));
     }
 }
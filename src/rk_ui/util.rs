@@ -20,4 +20,16 @@ pub fn get_key_colors(is_white: bool, is_active: bool) -> (Color, Color) {
         (false, false) => (Color::Black, Color::White), // Normal black key
         (false, true) => (Color::Gray, Color::White),  // Active black key
     }
-}
\ No newline at end of file
+}
+
+// Blue for rarely played through yellow to red for the most played
+pub fn heat_color(heat: f32) -> Color {
+    let heat = heat.clamp(0.0, 1.0);
+    if heat < 0.5 {
+        let t = heat * 2.0;
+        Color::Rgb((255.0 * t) as u8, (255.0 * t) as u8, (255.0 * (1.0 - t)) as u8)
+    } else {
+        let t = (heat - 0.5) * 2.0;
+        Color::Rgb(255, (255.0 * (1.0 - t)) as u8, 0)
+    }
+}
@@ -0,0 +1,292 @@
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    prelude::Color,
+    style::Style,
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph},
+};
+use std::{error::Error, sync::mpsc::Receiver, time::Duration};
+// ---
+use crate::pipeline::velocity::{CALIBRATION_NOTES, VelocityCurve, suggest_curve};
+use crate::types::midi::Message;
+
+// How far one key press moves a custom point or the fixed level
+const STEP: u8 = 4;
+
+enum Calibration {
+    Soft(Vec<u8>),
+    Loud(Vec<u8>, Vec<u8>),
+}
+
+struct VelocityView {
+    curve: VelocityCurve,
+    point: usize, // selected custom point
+    last: Option<u8>,
+    calibration: Option<Calibration>,
+    status: String,
+}
+
+impl VelocityView {
+    fn note_on(&mut self, velocity: u8) {
+        self.last = Some(velocity);
+        self.calibration = match self.calibration.take() {
+            Some(Calibration::Soft(mut soft)) => {
+                soft.push(velocity);
+                if soft.len() < CALIBRATION_NOTES {
+                    Some(Calibration::Soft(soft))
+                } else {
+                    Some(Calibration::Loud(soft, Vec::new()))
+                }
+            }
+            Some(Calibration::Loud(soft, mut loud)) => {
+                loud.push(velocity);
+                if loud.len() < CALIBRATION_NOTES {
+                    Some(Calibration::Loud(soft, loud))
+                } else {
+                    match suggest_curve(&soft, &loud) {
+                        Ok(curve) => {
+                            self.curve = curve;
+                            self.point = 0;
+                            self.status = "Suggested curve from calibration".to_string();
+                        }
+                        Err(e) => self.status = e,
+                    }
+                    None
+                }
+            }
+            None => None,
+        };
+    }
+
+    // Up and down move the level, left and right move a custom point's input
+    fn adjust(&mut self, input: i16, output: i16) {
+        let step = |value: u8, by: i16, min: u8, max: u8| {
+            (value as i16 + by * STEP as i16).clamp(min as i16, max as i16) as u8
+        };
+        match &mut self.curve {
+            VelocityCurve::Fixed(level) => *level = step(*level, output, 1, 127),
+            VelocityCurve::Custom(points) => {
+                let index = self.point;
+                let min = if index == 0 {
+                    0
+                } else {
+                    points[index - 1].0 + 1
+                };
+                let max = points.get(index + 1).map(|p| p.0 - 1).unwrap_or(127);
+                let (x, y) = &mut points[index];
+                *x = step(*x, input, min, max);
+                *y = step(*y, output, 1, 127);
+            }
+            _ => (),
+        }
+    }
+
+    // A new point halfway to the next one
+    fn add_point(&mut self) {
+        if let VelocityCurve::Custom(points) = &mut self.curve
+            && let Some(&(x1, _)) = points.get(self.point + 1)
+        {
+            let (x0, _) = points[self.point];
+            if x1 - x0 > 1 {
+                let x = x0 + (x1 - x0) / 2;
+                let y = self.curve.apply(x);
+                if let VelocityCurve::Custom(points) = &mut self.curve {
+                    points.insert(self.point + 1, (x, y));
+                    self.point += 1;
+                }
+            }
+        }
+    }
+
+    fn remove_point(&mut self) {
+        if let VelocityCurve::Custom(points) = &mut self.curve
+            && points.len() > 2
+        {
+            points.remove(self.point);
+            self.point = self.point.min(points.len() - 1);
+        }
+    }
+
+    fn select_point(&mut self) {
+        if let VelocityCurve::Custom(points) = &self.curve {
+            self.point = (self.point + 1) % points.len();
+        }
+    }
+}
+
+fn render_chart(f: &mut Frame, view: &VelocityView, area: Rect) {
+    let curve: Vec<(f64, f64)> = (0..=127)
+        .map(|v| (v as f64, view.curve.apply(v) as f64))
+        .collect();
+    let points: Vec<(f64, f64)> = match &view.curve {
+        VelocityCurve::Custom(points) => {
+            points.iter().map(|(x, y)| (*x as f64, *y as f64)).collect()
+        }
+        _ => Vec::new(),
+    };
+    let selected: Vec<(f64, f64)> = points.get(view.point).copied().into_iter().collect();
+    let last: Vec<(f64, f64)> = view
+        .last
+        .map(|v| (v as f64, view.curve.apply(v) as f64))
+        .into_iter()
+        .collect();
+
+    let datasets = vec![
+        Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&curve),
+        Dataset::default()
+            .marker(Marker::Block)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Yellow))
+            .data(&points),
+        Dataset::default()
+            .marker(Marker::Block)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Red))
+            .data(&selected),
+        Dataset::default()
+            .marker(Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Green))
+            .data(&last),
+    ];
+
+    let labels = || vec!["0", "64", "127"];
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(format!(" Velocity curve - {} ", view.curve.label()))
+                .borders(Borders::ALL),
+        )
+        .x_axis(
+            Axis::default()
+                .title("played")
+                .bounds([0.0, 127.0])
+                .labels(labels()),
+        )
+        .y_axis(
+            Axis::default()
+                .title("sent")
+                .bounds([0.0, 127.0])
+                .labels(labels()),
+        );
+    f.render_widget(chart, area);
+}
+
+fn render_info(f: &mut Frame, view: &VelocityView, area: Rect) {
+    let mut lines = vec![match view.last {
+        Some(v) => Line::from(format!("Last note: {} -> {}", v, view.curve.apply(v))),
+        None => Line::from("Play a note to preview"),
+    }];
+
+    if let VelocityCurve::Custom(points) = &view.curve {
+        lines.push(Line::from(""));
+        for (i, (x, y)) in points.iter().enumerate() {
+            let marker = if i == view.point { ">" } else { " " };
+            lines.push(Line::from(format!("{} {:>3} -> {:>3}", marker, x, y)));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(match &view.calibration {
+        Some(Calibration::Soft(soft)) => Line::styled(
+            format!("Play {} notes softly", CALIBRATION_NOTES - soft.len()),
+            Style::default().fg(Color::Yellow),
+        ),
+        Some(Calibration::Loud(_, loud)) => Line::styled(
+            format!("Play {} notes loudly", CALIBRATION_NOTES - loud.len()),
+            Style::default().fg(Color::Yellow),
+        ),
+        None => Line::from(view.status.as_str()),
+    });
+
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title(" Preview ").borders(Borders::ALL)),
+        area,
+    );
+}
+
+fn ui(f: &mut Frame, view: &VelocityView) {
+    let chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(f.area());
+    let columns = Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+        .split(chunks[0]);
+
+    render_chart(f, view, columns[0]);
+    render_info(f, view, columns[1]);
+    f.render_widget(
+        Paragraph::new(
+            "m curve | arrows adjust | tab point | a add | d delete | c calibrate | Enter save | q cancel",
+        ),
+        chunks[1],
+    );
+}
+
+// Edit a curve while previewing it on played notes, None if cancelled
+pub fn run_velocity_editor(
+    curve: VelocityCurve,
+    notes: Receiver<Message>,
+) -> Result<Option<VelocityCurve>, Box<dyn Error>> {
+    let mut view = VelocityView {
+        curve,
+        point: 0,
+        last: None,
+        calibration: None,
+        status: String::new(),
+    };
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let chosen = loop {
+        while let Ok((_, [status, _, velocity])) = notes.try_recv() {
+            if status & 0xf0 == 0x90 && velocity > 0 {
+                view.note_on(velocity);
+            }
+        }
+        terminal.draw(|f| ui(f, &view))?;
+
+        if event::poll(Duration::from_millis(30))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break None,
+                KeyCode::Enter => break Some(view.curve.clone()),
+                KeyCode::Char('m') => {
+                    view.curve = view.curve.next_kind();
+                    view.point = 0;
+                }
+                KeyCode::Up => view.adjust(0, 1),
+                KeyCode::Down => view.adjust(0, -1),
+                KeyCode::Left => view.adjust(-1, 0),
+                KeyCode::Right => view.adjust(1, 0),
+                KeyCode::Tab => view.select_point(),
+                KeyCode::Char('a') => view.add_point(),
+                KeyCode::Char('d') => view.remove_point(),
+                KeyCode::Char('c') => {
+                    view.calibration = Some(Calibration::Soft(Vec::new()));
+                    view.status.clear();
+                }
+                _ => (),
+            }
+        }
+    };
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(chosen)
+}
@@ -0,0 +1,153 @@
+use crate::types::midi::{Message, SUSTAIN};
+
+// Velocity histogram buckets of 16 steps each
+pub const VELOCITY_BUCKETS: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct PedalStats {
+    pub presses: u32,
+    pub held: u64, // micro seconds the pedal was down
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionStats {
+    pub duration: u64, // micro seconds from the first to the last message
+    pub note_count: u32,
+    pub velocity_histogram: [u32; VELOCITY_BUCKETS],
+    pub key_counts: [u32; 128],
+    pub total_note_length: u64, // micro seconds, summed over finished notes
+    pub finished_notes: u32,
+    pub pedal: PedalStats,
+}
+
+impl SessionStats {
+    pub fn from_messages(messages: &[Message]) -> Self {
+        let mut stats = SessionStats {
+            duration: 0,
+            note_count: 0,
+            velocity_histogram: [0; VELOCITY_BUCKETS],
+            key_counts: [0; 128],
+            total_note_length: 0,
+            finished_notes: 0,
+            pedal: PedalStats::default(),
+        };
+
+        let start = messages.first().map(|(t, _)| *t).unwrap_or(0);
+        let end = messages.last().map(|(t, _)| *t).unwrap_or(0);
+        stats.duration = end.saturating_sub(start);
+
+        let mut held: [Option<u64>; 128] = [None; 128];
+        let mut pedal_down: Option<u64> = None;
+
+        for (time, [status, data1, data2]) in messages {
+            let note = (*data1 & 0x7f) as usize;
+            match status {
+                0x90..=0x9f if *data2 > 0 => {
+                    stats.note_count += 1;
+                    stats.key_counts[note] += 1;
+                    stats.velocity_histogram
+                        [(*data2 as usize * VELOCITY_BUCKETS / 128).min(VELOCITY_BUCKETS - 1)] += 1;
+                    held[note] = Some(*time);
+                }
+                0x80..=0x9f => {
+                    if let Some(start) = held[note].take() {
+                        stats.total_note_length += time.saturating_sub(start);
+                        stats.finished_notes += 1;
+                    }
+                }
+                0xb0..=0xbf if *data1 == SUSTAIN => match (pedal_down, *data2 >= 64) {
+                    (None, true) => {
+                        stats.pedal.presses += 1;
+                        pedal_down = Some(*time);
+                    }
+                    (Some(down), false) => {
+                        stats.pedal.held += time.saturating_sub(down);
+                        pedal_down = None;
+                    }
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+
+        // still down at the end of the session
+        if let Some(down) = pedal_down {
+            stats.pedal.held += end.saturating_sub(down);
+        }
+
+        stats
+    }
+
+    pub fn notes_per_minute(&self) -> f32 {
+        if self.duration == 0 {
+            return 0.0;
+        }
+        self.note_count as f32 * 60_000_000.0 / self.duration as f32
+    }
+
+    pub fn distinct_pitches(&self) -> usize {
+        self.key_counts.iter().filter(|count| **count > 0).count()
+    }
+
+    // Micro seconds
+    pub fn average_note_length(&self) -> u64 {
+        self.total_note_length / self.finished_notes.max(1) as u64
+    }
+
+    // Share of the session with the sustain pedal down, 0.0 - 100.0
+    pub fn pedal_percent(&self) -> f32 {
+        if self.duration == 0 {
+            return 0.0;
+        }
+        self.pedal.held as f32 * 100.0 / self.duration as f32
+    }
+
+    // Played keys by count, most played first
+    pub fn ranked_keys(&self) -> Vec<(u8, u32)> {
+        let mut ranked: Vec<(u8, u32)> = (0..128u8)
+            .map(|note| (note, self.key_counts[note as usize]))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+
+    // Play count of a key relative to the most played key, 0.0 - 1.0
+    pub fn heat(&self, note: u8) -> f32 {
+        let max = self.key_counts.iter().max().copied().unwrap_or(0);
+        if max == 0 {
+            return 0.0;
+        }
+        self.key_counts[note as usize] as f32 / max as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_stats() {
+        let stats = SessionStats::from_messages(&[
+            (0, [0xb0, SUSTAIN, 127]),
+            (0, [0x90, 60, 100]),
+            (500_000, [0x80, 60, 0]),
+            (1_000_000, [0xb0, SUSTAIN, 0]),
+            (1_000_000, [0x90, 64, 20]),
+            (1_500_000, [0x90, 60, 0]),
+            (2_000_000, [0x90, 60, 110]),
+            (3_000_000, [0x80, 64, 0]),
+        ]);
+
+        assert_eq!(stats.note_count, 3);
+        assert_eq!(stats.distinct_pitches(), 2);
+        assert_eq!(stats.notes_per_minute(), 60.0);
+        assert_eq!(stats.velocity_histogram[6], 2);
+        assert_eq!(stats.velocity_histogram[1], 1);
+        assert_eq!(stats.ranked_keys()[0], (60, 2));
+        // the last C is still held, the E lasted two seconds
+        assert_eq!(stats.average_note_length(), 1_250_000);
+        assert_eq!(stats.pedal.presses, 1);
+        assert!((stats.pedal_percent() - 33.3).abs() < 0.1);
+    }
+}
@@ -0,0 +1,224 @@
+use musical_note::{Accidental, Key, NoteName, Scale, midi_to_note};
+
+use crate::rk_ui::constants::{KEY_NAMES, PIANO_PATTERN};
+
+// A pitch as it is written: letter, alteration in semitones and
+// scientific octave (middle C is C4)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpelledPitch {
+    pub step: NoteName,
+    pub alter: i8,
+    pub octave: i8,
+}
+
+pub fn step_char(step: NoteName) -> char {
+    match step {
+        NoteName::C => 'C',
+        NoteName::D => 'D',
+        NoteName::E => 'E',
+        NoteName::F => 'F',
+        NoteName::G => 'G',
+        NoteName::A => 'A',
+        NoteName::B => 'B',
+    }
+}
+
+// Position on the staff in letter steps, C-1 is 0 and middle C is 35
+pub fn diatonic_index(pitch: SpelledPitch) -> i32 {
+    let step = match pitch.step {
+        NoteName::C => 0,
+        NoteName::D => 1,
+        NoteName::E => 2,
+        NoteName::F => 3,
+        NoteName::G => 4,
+        NoteName::A => 5,
+        NoteName::B => 6,
+    };
+    (pitch.octave as i32 + 1) * 7 + step
+}
+
+pub fn alter(accidental: Accidental) -> i8 {
+    match accidental {
+        Accidental::DoubleFlat => -2,
+        Accidental::Flat => -1,
+        Accidental::White => 0,
+        Accidental::Sharp => 1,
+        Accidental::DoubleSharp => 2,
+    }
+}
+
+// Pitch classes (0 = C) of the seven scale degrees, tonic first
+pub fn scale_pitch_classes(key: Key) -> [u8; 7] {
+    let mut degrees = [key.get_root()[0]; 7];
+    for (index, interval) in key.scale.structure().iter().enumerate() {
+        degrees[index + 1] = (degrees[index] + interval) % 12;
+    }
+    degrees
+}
+
+// Spell a MIDI note using the accidentals of the key
+pub fn spell(midi: u8, key: Key) -> SpelledPitch {
+    let in_scale = scale_pitch_classes(key).contains(&(midi % 12));
+    let accidental = match PIANO_PATTERN[(midi % 12) as usize] {
+        // chromatic white keys read better as naturals than as Cb or E#
+        true if !in_scale => Some(Accidental::White),
+        _ => None,
+    };
+    let resolved = midi_to_note(midi, key, accidental);
+    let mut octave = (midi / 12) as i8 - 1;
+
+    // B# and Cb belong to the neighbouring octave
+    match (resolved.note, midi % 12) {
+        (NoteName::B, 0 | 1) => octave -= 1,
+        (NoteName::C, 10 | 11) => octave += 1,
+        _ => (),
+    }
+
+    SpelledPitch {
+        step: resolved.note,
+        alter: alter(resolved.accidental),
+        octave,
+    }
+}
+
+// Sharps (positive) or flats (negative) in the key signature
+pub fn fifths(key: Key) -> i8 {
+    let (note, accidental) = key.tonic;
+    let natural = match note {
+        NoteName::F => -1,
+        NoteName::C => 0,
+        NoteName::G => 1,
+        NoteName::D => 2,
+        NoteName::A => 3,
+        NoteName::E => 4,
+        NoteName::B => 5,
+    };
+    // offset from the major key with the same tonic
+    let mode = match key.scale {
+        Scale::Lydian => 1,
+        Scale::Major => 0,
+        Scale::Mixolidyan => -1,
+        Scale::Dorian => -2,
+        Scale::Minor => -3,
+        Scale::Phrygian => -4,
+        Scale::Locrian => -5,
+    };
+    natural + alter(accidental) * 7 + mode
+}
+
+// Alteration the key signature gives to a letter
+pub fn signature_alter(key: Key, step: NoteName) -> i8 {
+    const SHARPS: [NoteName; 7] = [
+        NoteName::F,
+        NoteName::C,
+        NoteName::G,
+        NoteName::D,
+        NoteName::A,
+        NoteName::E,
+        NoteName::B,
+    ];
+    let count = fifths(key);
+    let position = SHARPS.iter().position(|s| *s == step).unwrap_or(0) as i8;
+    if count > 0 && position < count {
+        1
+    } else if count < 0 && 6 - position < -count {
+        -1
+    } else {
+        0
+    }
+}
+
+pub fn mode_name(scale: Scale) -> &'static str {
+    match scale {
+        Scale::Major => "major",
+        Scale::Minor => "minor",
+        Scale::Dorian => "dorian",
+        Scale::Phrygian => "phrygian",
+        Scale::Lydian => "lydian",
+        Scale::Mixolidyan => "mixolydian",
+        Scale::Locrian => "locrian",
+    }
+}
+
+// Tonic as text, e.g. "Bb" or "F#"
+pub fn tonic_name(key: Key) -> String {
+    let (note, accidental) = key.tonic;
+    let suffix = match accidental {
+        Accidental::DoubleFlat => "bb",
+        Accidental::Flat => "b",
+        Accidental::White => "",
+        Accidental::Sharp => "#",
+        Accidental::DoubleSharp => "##",
+    };
+    format!("{}{}", step_char(note), suffix)
+}
+
+pub fn key_name(key: Key) -> String {
+    format!("{} {}", tonic_name(key), key.scale.to_string())
+}
+
+// Parse "C", "Bb", "F#m", "Eb minor" or "D dorian"
+pub fn parse_key(text: &str) -> Option<Key> {
+    let text = text.trim();
+    let mut chars = text.chars();
+    let note = NoteName::from_str(&chars.next()?.to_string())?;
+    let rest = chars.as_str();
+
+    let (accidental, rest) = if let Some(rest) = rest.strip_prefix('#') {
+        (Accidental::Sharp, rest)
+    } else if let Some(rest) = rest.strip_prefix('b') {
+        (Accidental::Flat, rest)
+    } else {
+        (Accidental::White, rest)
+    };
+
+    let scale = match rest.trim().to_lowercase().as_str() {
+        "" | "maj" | "major" => Scale::Major,
+        "m" | "min" | "minor" => Scale::Minor,
+        "dorian" => Scale::Dorian,
+        "phrygian" => Scale::Phrygian,
+        "lydian" => Scale::Lydian,
+        "mixolydian" => Scale::Mixolidyan,
+        "locrian" => Scale::Locrian,
+        _ => return None,
+    };
+
+    Some(Key::new(note, accidental, scale))
+}
+
+// Scientific name of a MIDI note, middle C is C4
+pub fn note_name(note: u8) -> String {
+    format!(
+        "{}{}",
+        KEY_NAMES[(note % 12) as usize],
+        (note / 12) as i32 - 1
+    )
+}
+
+// Parse a key as "C3", "F#2", "Bb4" or a MIDI number
+pub fn parse_note(text: &str) -> Option<u8> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<u8>() {
+        return (number < 128).then_some(number);
+    }
+
+    let mut chars = text.chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    let mut pitch = KEY_NAMES
+        .iter()
+        .position(|name| *name == letter.to_string())? as i32;
+    let rest = chars.as_str();
+    let rest = if let Some(rest) = rest.strip_prefix('#') {
+        pitch += 1;
+        rest
+    } else if let Some(rest) = rest.strip_prefix('b') {
+        pitch -= 1;
+        rest
+    } else {
+        rest
+    };
+    let octave = rest.parse::<i32>().ok()?;
+    u8::try_from(pitch + (octave + 1) * 12)
+        .ok()
+        .filter(|note| *note < 128)
+}
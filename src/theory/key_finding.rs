@@ -0,0 +1,201 @@
+use musical_note::{Accidental, Key, NoteName, Scale};
+
+use crate::types::midi::Message;
+
+// Krumhansl-Kessler key profiles, tonic first
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+// Spelling of each tonic pitch class, as usually written
+const MAJOR_TONICS: [(NoteName, Accidental); 12] = [
+    (NoteName::C, Accidental::White),
+    (NoteName::D, Accidental::Flat),
+    (NoteName::D, Accidental::White),
+    (NoteName::E, Accidental::Flat),
+    (NoteName::E, Accidental::White),
+    (NoteName::F, Accidental::White),
+    (NoteName::F, Accidental::Sharp),
+    (NoteName::G, Accidental::White),
+    (NoteName::A, Accidental::Flat),
+    (NoteName::A, Accidental::White),
+    (NoteName::B, Accidental::Flat),
+    (NoteName::B, Accidental::White),
+];
+const MINOR_TONICS: [(NoteName, Accidental); 12] = [
+    (NoteName::C, Accidental::White),
+    (NoteName::C, Accidental::Sharp),
+    (NoteName::D, Accidental::White),
+    (NoteName::E, Accidental::Flat),
+    (NoteName::E, Accidental::White),
+    (NoteName::F, Accidental::White),
+    (NoteName::F, Accidental::Sharp),
+    (NoteName::G, Accidental::White),
+    (NoteName::G, Accidental::Sharp),
+    (NoteName::A, Accidental::White),
+    (NoteName::B, Accidental::Flat),
+    (NoteName::B, Accidental::White),
+];
+
+// Time each pitch class sounds for, in seconds
+pub fn pitch_class_histogram(messages: &[Message]) -> [f32; 12] {
+    let mut histogram = [0.0; 12];
+    let mut held: [Option<u64>; 128] = [None; 128];
+
+    for (time, [status, note, velocity]) in messages {
+        let index = (*note & 0x7f) as usize;
+        match status {
+            0x90..=0x9f if *velocity > 0 => held[index] = Some(*time),
+            0x80..=0x9f => {
+                if let Some(start) = held[index].take() {
+                    histogram[index % 12] += time.saturating_sub(start) as f32 / 1_000_000.0;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    histogram
+}
+
+// Time each pitch class sounds for between `from` and `to`, in seconds. Notes
+// still held at `to` count up to it, so the latest key shows while it is played.
+pub fn window_histogram(messages: &[Message], from: u64, to: u64) -> [f32; 12] {
+    let mut histogram = [0.0; 12];
+    let mut held: [Option<u64>; 128] = [None; 128];
+    let mut add = |note: usize, start: u64, end: u64| {
+        let overlap = end.min(to).saturating_sub(start.max(from));
+        histogram[note % 12] += overlap as f32 / 1_000_000.0;
+    };
+
+    for (time, [status, note, velocity]) in messages.iter().take_while(|(t, _)| *t <= to) {
+        let index = (*note & 0x7f) as usize;
+        match status {
+            0x90..=0x9f if *velocity > 0 => {
+                // struck again while held, the first sounding ends here
+                if let Some(start) = held[index].replace(*time) {
+                    add(index, start, *time);
+                }
+            }
+            0x80..=0x9f => {
+                if let Some(start) = held[index].take() {
+                    add(index, start, *time);
+                }
+            }
+            _ => (),
+        }
+    }
+    for (index, start) in held.iter().enumerate() {
+        if let Some(start) = start {
+            add(index, *start, to);
+        }
+    }
+
+    histogram
+}
+
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+    let mut numerator = 0.0;
+    let mut sum_a = 0.0;
+    let mut sum_b = 0.0;
+    for i in 0..12 {
+        let da = a[i] - mean_a;
+        let db = b[i] - mean_b;
+        numerator += da * db;
+        sum_a += da * da;
+        sum_b += db * db;
+    }
+    if sum_a == 0.0 || sum_b == 0.0 {
+        return 0.0;
+    }
+    numerator / (sum_a * sum_b).sqrt()
+}
+
+// Best matching major or minor key and its correlation (-1.0 - 1.0)
+pub fn find_key(histogram: &[f32; 12]) -> Option<(Key, f32)> {
+    if histogram.iter().all(|weight| *weight <= 0.0) {
+        return None;
+    }
+
+    let mut best: Option<(Key, f32)> = None;
+    for tonic in 0..12 {
+        let mut rotated = [0.0; 12];
+        for (pitch_class, weight) in histogram.iter().enumerate() {
+            rotated[(pitch_class + 12 - tonic) % 12] = *weight;
+        }
+
+        for (profile, tonics, scale) in [
+            (&MAJOR_PROFILE, &MAJOR_TONICS, Scale::Major),
+            (&MINOR_PROFILE, &MINOR_TONICS, Scale::Minor),
+        ] {
+            let score = correlation(&rotated, profile);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                let (note, accidental) = tonics[tonic];
+                best = Some((Key::new(note, accidental, scale), score));
+            }
+        }
+    }
+
+    best
+}
+
+pub fn detect_key(messages: &[Message]) -> Option<Key> {
+    find_key(&pitch_class_histogram(messages)).map(|(key, _)| key)
+}
+
+// The key of the last `window` micro seconds before `now`, and how well it fits
+pub fn detect_recent_key(messages: &[Message], now: u64, window: u64) -> Option<(Key, f32)> {
+    // messages are in time order, so the scan starts at the window. Notes held
+    // from before it are left out.
+    let from = now.saturating_sub(window);
+    let start = messages.partition_point(|(time, _)| *time < from);
+    find_key(&window_histogram(&messages[start..], from, now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theory::key::key_name;
+
+    #[test]
+    fn test_detects_scale_keys() {
+        let scale = |notes: &[u8]| -> Vec<Message> {
+            notes
+                .iter()
+                .enumerate()
+                .flat_map(|(i, note)| {
+                    let start = i as u64 * 500_000;
+                    // tonic held longer, as a piece would end on it
+                    let length = if i == 0 { 1_500_000 } else { 400_000 };
+                    [
+                        (start, [0x90, *note, 64]),
+                        (start + length, [0x80, *note, 0]),
+                    ]
+                })
+                .collect()
+        };
+
+        let g_major = scale(&[67, 69, 71, 72, 74, 76, 78, 79]);
+        assert_eq!(key_name(detect_key(&g_major).unwrap()), "G major");
+
+        let a_minor = scale(&[57, 59, 60, 62, 64, 65, 67, 69, 64, 60]);
+        assert_eq!(key_name(detect_key(&a_minor).unwrap()), "A minor");
+
+        assert!(detect_key(&[]).is_none());
+
+        // a sliding window follows the music into the new key, counting a held note
+        let mut modulating = g_major.clone();
+        modulating.extend(a_minor.iter().map(|(t, m)| (t + 10_000_000, *m)));
+        modulating.pop();
+        let (key, fit) = detect_recent_key(&modulating, 16_000_000, 6_000_000).unwrap();
+        assert_eq!(key_name(key), "A minor");
+        assert!(fit > 0.5);
+        let (key, _) = detect_recent_key(&modulating, 5_000_000, 6_000_000).unwrap();
+        assert_eq!(key_name(key), "G major");
+    }
+}
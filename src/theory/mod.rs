@@ -0,0 +1,4 @@
+pub mod key;
+pub mod key_finding;
+pub mod scala;
+pub mod tuning;
@@ -0,0 +1,171 @@
+use std::{fs, path::Path};
+
+use crate::theory::tuning::{KeyboardMap, Scale, Tuning};
+
+// Lines starting with ! are comments in both file types
+fn content_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.starts_with('!'))
+}
+
+// A pitch line: cents when it has a period, otherwise a ratio like 3/2 or 2
+fn parse_pitch(line: &str) -> Result<f64, String> {
+    let value = line.split_whitespace().next().unwrap_or("");
+    if value.contains('.') {
+        return value
+            .parse::<f64>()
+            .map_err(|_| format!("Bad cents value \"{}\"", value));
+    }
+
+    let (numerator, denominator) = value.split_once('/').unwrap_or((value, "1"));
+    match (numerator.parse::<f64>(), denominator.parse::<f64>()) {
+        (Ok(n), Ok(d)) if n > 0.0 && d > 0.0 => Ok(1200.0 * (n / d).log2()),
+        _ => Err(format!("Bad ratio \"{}\"", value)),
+    }
+}
+
+// A .scl file: description, number of notes, then one pitch per line
+pub fn parse_scl(text: &str) -> Result<Scale, String> {
+    let mut lines = content_lines(text);
+    let description = lines.next().ok_or("Empty scale file")?.trim().to_string();
+    let mut lines = lines.filter(|line| !line.trim().is_empty());
+
+    let count = lines
+        .next()
+        .and_then(|line| line.split_whitespace().next()?.parse::<usize>().ok())
+        .ok_or("Missing number of notes")?;
+    if count == 0 {
+        return Err("The scale has no notes".to_string());
+    }
+    let cents = lines
+        .take(count)
+        .map(parse_pitch)
+        .collect::<Result<Vec<f64>, String>>()?;
+    if cents.len() < count {
+        return Err(format!("Expected {} notes, found {}", count, cents.len()));
+    }
+
+    Ok(Scale { description, cents })
+}
+
+// A .kbm file: map size, first and last key, middle key, reference key and
+// frequency, octave degree, then the map with x for unmapped keys
+pub fn parse_kbm(text: &str, scale_size: usize) -> Result<KeyboardMap, String> {
+    let mut values = content_lines(text)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string);
+    let mut next = |name: &str| values.next().ok_or(format!("Missing {}", name));
+    let key = |value: String, name: &str| {
+        value
+            .parse::<u8>()
+            .ok()
+            .filter(|key| *key < 128)
+            .ok_or(format!("Bad {} \"{}\"", name, value))
+    };
+
+    let size = next("map size")?
+        .parse::<usize>()
+        .map_err(|_| "Bad map size")?;
+    let first = key(next("first key")?, "first key")?;
+    let last = key(next("last key")?, "last key")?;
+    let middle = key(next("middle key")?, "middle key")?;
+    let reference = key(next("reference key")?, "reference key")?;
+    let frequency = next("reference frequency")?
+        .parse::<f64>()
+        .ok()
+        .filter(|f| *f > 0.0)
+        .ok_or("Bad reference frequency")?;
+    let octave_degree = match next("octave degree")?.parse::<i32>() {
+        Ok(0) => scale_size as i32,
+        Ok(degree) => degree,
+        Err(_) => return Err("Bad octave degree".to_string()),
+    };
+
+    // entries left out are unmapped
+    let mut map = Vec::with_capacity(size);
+    for _ in 0..size {
+        map.push(match values.next().as_deref() {
+            None | Some("x") => None,
+            Some(value) => Some(
+                value
+                    .parse::<i32>()
+                    .map_err(|_| format!("Bad map entry \"{}\"", value))?,
+            ),
+        });
+    }
+
+    Ok(KeyboardMap {
+        map,
+        first,
+        last,
+        middle,
+        reference,
+        frequency,
+        octave_degree,
+    })
+}
+
+pub fn load_scala(scl: &Path, kbm: Option<&Path>) -> Result<Tuning, String> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    };
+    let scale = parse_scl(&read(scl)?)?;
+    let map = match kbm {
+        Some(kbm) => parse_kbm(&read(kbm)?, scale.cents.len())?,
+        None => KeyboardMap::linear(),
+    };
+    let name = scl
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or(scale.description.clone());
+    Ok(Tuning::new(&name, scale, map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theory::tuning::Temperament;
+
+    const PENTATONIC: &str = "! slendro-ish.scl
+!
+Five equal steps
+ 5
+!
+ 240.0
+ 480.
+ 720.0 cents
+ 960.0
+ 2/1
+";
+
+    #[test]
+    fn test_scala_tunings() {
+        let scale = parse_scl(PENTATONIC).unwrap();
+        assert_eq!(scale.description, "Five equal steps");
+        assert_eq!(scale.cents, vec![240.0, 480.0, 720.0, 960.0, 1200.0]);
+        assert!(parse_scl("Too short\n3\n100.0\n").is_err());
+        assert!(parse_scl("Bad\n1\n3/0\n").is_err());
+
+        // A4 stays at 440 Hz, each key a fifth of an octave above the last
+        let tuning = Tuning::new("test", scale.clone(), KeyboardMap::linear());
+        assert!((tuning.pitch(69).unwrap() - 69.0).abs() < 1e-9);
+        assert!((tuning.pitch(70).unwrap() - 71.4).abs() < 1e-9);
+        assert!((tuning.frequency(74).unwrap() - 880.0).abs() < 1e-3);
+        assert!(!tuning.is_equal());
+
+        // white keys only from C, black keys silent, D4 at 300 Hz
+        let kbm = "! white.kbm\n12\n0\n127\n60\n62\n300.0\n5\n0\nx\n1\nx\n2\n3\nx\n4\nx\nx\nx\nx\n";
+        let mapped = Tuning::new("test", scale, parse_kbm(kbm, 5).unwrap());
+        assert_eq!(mapped.pitch(61), None);
+        assert!((mapped.frequency(62).unwrap() - 300.0).abs() < 1e-3);
+        assert!((mapped.frequency(72).unwrap() - 2.0 * mapped.frequency(60).unwrap()).abs() < 1e-3);
+
+        assert!(Tuning::equal().is_equal());
+        let just = Temperament::Just.tuning();
+        assert!((just.pitch(69).unwrap() - 69.0).abs() < 1e-9);
+        // a just major third is about 14 cents narrower than an equal one
+        let third = just.pitch(64).unwrap() - just.pitch(60).unwrap();
+        assert!((third - 4.0 + 0.1369).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::theory::scala::load_scala;
+
+// Concert pitch, where every built-in tuning is pinned
+const A4: u8 = 69;
+const A4_FREQUENCY: f64 = 440.0;
+// Keys closer than this to equal temperament play unchanged
+const EQUAL_TOLERANCE_CENTS: f64 = 0.01;
+
+// A scale as cents above its first degree, the last entry being the period
+// (1200 for an octave). Degree 0 is implied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scale {
+    pub description: String,
+    pub cents: Vec<f64>,
+}
+
+// Which scale degree each key plays and where the scale is pinned, as in a .kbm file
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardMap {
+    pub map: Vec<Option<i32>>, // degrees for one repeat of the pattern, empty for one per key
+    pub first: u8,
+    pub last: u8,
+    pub middle: u8,    // key playing degree 0
+    pub reference: u8, // key sounding at the reference frequency
+    pub frequency: f64,
+    pub octave_degree: i32, // degree the pattern moves by each time it repeats
+}
+
+impl KeyboardMap {
+    // Consecutive keys play consecutive degrees from middle C, with A4 at 440 Hz
+    pub fn linear() -> Self {
+        KeyboardMap {
+            map: Vec::new(),
+            first: 0,
+            last: 127,
+            middle: 60,
+            reference: A4,
+            frequency: A4_FREQUENCY,
+            octave_degree: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuning {
+    pub name: String,
+    scale: Scale,
+    map: KeyboardMap,
+}
+
+impl Tuning {
+    pub fn new(name: &str, scale: Scale, map: KeyboardMap) -> Self {
+        Tuning {
+            name: name.to_string(),
+            scale,
+            map,
+        }
+    }
+
+    pub fn equal() -> Self {
+        Tuning::new(
+            "12-TET",
+            Scale {
+                description: "12 tone equal temperament".to_string(),
+                cents: (1..=12).map(|step| step as f64 * 100.0).collect(),
+            },
+            KeyboardMap::linear(),
+        )
+    }
+
+    fn degree(&self, key: u8) -> Option<i32> {
+        let steps = key as i32 - self.map.middle as i32;
+        if self.map.map.is_empty() {
+            return Some(steps);
+        }
+        let size = self.map.map.len() as i32;
+        let degree = self.map.map[steps.rem_euclid(size) as usize]?;
+        Some(degree + steps.div_euclid(size) * self.map.octave_degree)
+    }
+
+    fn cents(&self, degree: i32) -> f64 {
+        let size = self.scale.cents.len().max(1) as i32;
+        let period = self.scale.cents.last().copied().unwrap_or(1200.0);
+        let step = match degree.rem_euclid(size) {
+            0 => 0.0,
+            step => self.scale.cents[step as usize - 1],
+        };
+        degree.div_euclid(size) as f64 * period + step
+    }
+
+    // Sounding pitch of a key in fractional MIDI notes, 69.0 being A440.
+    // None for keys the mapping leaves silent.
+    pub fn pitch(&self, key: u8) -> Option<f64> {
+        if !(self.map.first..=self.map.last).contains(&key) {
+            return None;
+        }
+        let degree = self.degree(key)?;
+        let reference = self
+            .degree(self.map.reference)
+            .unwrap_or(self.map.reference as i32 - self.map.middle as i32);
+        let offset = 12.0 * (self.map.frequency / A4_FREQUENCY).log2();
+        Some(A4 as f64 + offset + (self.cents(degree) - self.cents(reference)) / 100.0)
+    }
+
+    pub fn frequency(&self, key: u8) -> Option<f32> {
+        self.pitch(key)
+            .map(|pitch| (A4_FREQUENCY * 2f64.powf((pitch - A4 as f64) / 12.0)) as f32)
+    }
+
+    // Whether every key plays as in 12-TET, so synths can skip retuning
+    pub fn is_equal(&self) -> bool {
+        (0..128u8).all(|key| {
+            self.pitch(key)
+                .is_some_and(|pitch| (pitch - key as f64).abs() * 100.0 < EQUAL_TOLERANCE_CENTS)
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Temperament {
+    Just,
+    Meantone,
+    Pythagorean,
+    Werckmeister,
+}
+
+fn ratio_cents(numerator: f64, denominator: f64) -> f64 {
+    1200.0 * (numerator / denominator).log2()
+}
+
+impl Temperament {
+    pub const ALL: [Temperament; 4] = [
+        Temperament::Just,
+        Temperament::Meantone,
+        Temperament::Pythagorean,
+        Temperament::Werckmeister,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Temperament::Just => "just intonation",
+            Temperament::Meantone => "quarter-comma meantone",
+            Temperament::Pythagorean => "Pythagorean",
+            Temperament::Werckmeister => "Werckmeister III",
+        }
+    }
+
+    // Twelve notes from C, with A kept at 440 Hz
+    pub fn tuning(self) -> Tuning {
+        let cents: Vec<f64> = match self {
+            Temperament::Just => [
+                (16, 15),
+                (9, 8),
+                (6, 5),
+                (5, 4),
+                (4, 3),
+                (45, 32),
+                (3, 2),
+                (8, 5),
+                (5, 3),
+                (9, 5),
+                (15, 8),
+                (2, 1),
+            ]
+            .iter()
+            .map(|&(n, d)| ratio_cents(n as f64, d as f64))
+            .collect(),
+            Temperament::Pythagorean => [
+                (256, 243),
+                (9, 8),
+                (32, 27),
+                (81, 64),
+                (4, 3),
+                (729, 512),
+                (3, 2),
+                (128, 81),
+                (27, 16),
+                (16, 9),
+                (243, 128),
+                (2, 1),
+            ]
+            .iter()
+            .map(|&(n, d)| ratio_cents(n as f64, d as f64))
+            .collect(),
+            Temperament::Meantone => vec![
+                76.049, 193.157, 310.265, 386.314, 503.422, 579.471, 696.578, 772.627, 889.735,
+                1006.843, 1082.892, 1200.0,
+            ],
+            Temperament::Werckmeister => vec![
+                90.225, 192.180, 294.135, 390.225, 498.045, 588.270, 696.090, 792.180, 888.270,
+                996.090, 1092.180, 1200.0,
+            ],
+        };
+        Tuning::new(
+            self.label(),
+            Scale {
+                description: self.label().to_string(),
+                cents,
+            },
+            KeyboardMap::linear(),
+        )
+    }
+}
+
+// The tuning chosen for sessions, kept in the preferences
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum TuningChoice {
+    #[default]
+    Equal,
+    Builtin(Temperament),
+    Scala {
+        scale: PathBuf,
+        mapping: Option<PathBuf>, // .kbm, linear from middle C when absent
+    },
+}
+
+impl TuningChoice {
+    pub fn load(&self) -> Result<Tuning, String> {
+        match self {
+            TuningChoice::Equal => Ok(Tuning::equal()),
+            TuningChoice::Builtin(temperament) => Ok(temperament.tuning()),
+            TuningChoice::Scala { scale, mapping } => load_scala(scale, mapping.as_deref()),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            TuningChoice::Equal => "12-TET".to_string(),
+            TuningChoice::Builtin(temperament) => temperament.label().to_string(),
+            TuningChoice::Scala { scale, .. } => scale
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
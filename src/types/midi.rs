@@ -2,6 +2,11 @@
 pub type MessageData = [u8; 3]; // [message_type, note, velocity]
 // time-stamped data
 pub type Message = (u64, MessageData);
+
+// Controller numbers of the control change messages the synths act on
+pub const SUSTAIN: u8 = 64; // values from 64 are down
+pub const ALL_SOUND_OFF: u8 = 120;
+pub const ALL_NOTES_OFF: u8 = 123;
 pub struct MessageLog<const L: usize> {
     pub data: [(u64, [u8; 3]); L],
 }
@@ -1 +1,2 @@
 pub mod midi;
+pub mod recording;
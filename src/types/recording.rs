@@ -0,0 +1,88 @@
+use std::{
+    env,
+    fs::{self, create_dir_all},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use musical_note::Key;
+use serde::{Deserialize, Serialize};
+
+use crate::types::midi::Message;
+
+// Everything played during a session, as received from the watcher
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recording {
+    pub title: String,
+    pub recorded_at: u64, // Unix timestamp
+    pub bpm: f32,
+    pub time_signature: (u8, u8), // (beats, beat type)
+    pub key: Option<Key>,
+    pub messages: Vec<Message>, // micro seconds from the first message
+}
+
+pub fn recording_dir() -> PathBuf {
+    PathBuf::from(env::var("RECORDING_DIR").unwrap_or("recordings".to_string()))
+}
+
+impl Recording {
+    pub fn new(title: &str) -> Self {
+        Recording {
+            title: title.to_string(),
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            bpm: 120.0,
+            time_signature: (4, 4),
+            key: None,
+            messages: Vec::new(),
+        }
+    }
+
+    // Raw timestamps are kept until the recording is saved
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // Micro seconds from the first to the last message
+    pub fn duration(&self) -> u64 {
+        match (self.messages.first(), self.messages.last()) {
+            (Some((first, _)), Some((last, _))) => last - first,
+            _ => 0,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let dir = recording_dir();
+        create_dir_all(&dir)?;
+
+        // timestamps are stored relative to the first message
+        let start = self.messages.first().map(|(t, _)| *t).unwrap_or(0);
+        let normalised = Recording {
+            messages: self
+                .messages
+                .iter()
+                .map(|(t, data)| (t.saturating_sub(start), *data))
+                .collect(),
+            ..self.clone()
+        };
+
+        let path = dir.join(format!("{}.ron", self.recorded_at));
+        let text = ron::ser::to_string_pretty(&normalised, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(&path, text)?;
+
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(io::Error::other)
+    }
+}
@@ -1 +1,3 @@
-pub mod logger;
\ No newline at end of file
+pub mod logger;
+pub mod date;
+pub mod prefs;
@@ -0,0 +1,98 @@
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf, sync::Once};
+
+use musical_note::Key;
+use serde::{Deserialize, Serialize};
+// ---
+use crate::audio::device::OutputSettings;
+use crate::audio::effects::EffectSettings;
+use crate::audio::oscillator::Waveform;
+use crate::pipeline::arp::ArpSettings;
+use crate::pipeline::harmony::HarmonySettings;
+use crate::pipeline::transpose::Transpose;
+use crate::pipeline::velocity::VelocityCurve;
+use crate::pipeline::zones::Zone;
+use crate::rk_ui::types::{KeyboardSettings, ScaleHighlight};
+use crate::theory::tuning::TuningChoice;
+
+// What the live synth plays through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Instrument {
+    #[default]
+    Soundfont,
+    Builtin(Waveform),
+}
+
+impl Instrument {
+    pub fn label(self) -> String {
+        match self {
+            Instrument::Soundfont => "soundfont".to_string(),
+            Instrument::Builtin(waveform) => format!("built-in {}", waveform.label()),
+        }
+    }
+}
+
+// Choices remembered between runs. Fields default so older files keep loading.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Prefs {
+    pub soundfont: Option<PathBuf>,
+    pub instrument: Instrument,
+    pub output: OutputSettings,
+    pub effects: EffectSettings,
+    pub effect_presets: BTreeMap<String, EffectSettings>,
+    pub velocity_curve: VelocityCurve,
+    pub transpose: Transpose,
+    pub zones: Vec<Zone>,
+    pub tuning: TuningChoice,
+    pub arp: ArpSettings,
+    pub harmony: HarmonySettings,
+    pub midi_thru: Option<String>, // output port that gets everything played
+    pub keyboard: KeyboardSettings,
+    pub scale_highlight: ScaleHighlight,
+    pub practice_scale: Option<Key>, // for improvising over
+}
+
+static REPORT_UNREADABLE: Once = Once::new();
+
+fn prefs_path() -> PathBuf {
+    PathBuf::from(env::var("PREFS_FILE").unwrap_or("prefs.ron".to_string()))
+}
+
+impl Prefs {
+    // The saved preferences, None when there are none yet
+    fn read() -> Result<Option<Self>, String> {
+        let path = prefs_path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        ron::from_str(&text)
+            .map(Some)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    }
+
+    // Defaults for anything missing. A file that cannot be read is reported once,
+    // and left alone by `save` so it can be fixed by hand.
+    pub fn load() -> Self {
+        Prefs::read()
+            .unwrap_or_else(|e| {
+                REPORT_UNREADABLE.call_once(|| eprintln!("{}, using the defaults.", e));
+                None
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Err(e) = Prefs::read() {
+            return Err(io::Error::other(format!("{}, not saving over it", e)));
+        }
+        let path = prefs_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+}